@@ -0,0 +1,68 @@
+//! Parse and write throughput on large generated saves.
+//!
+//! Run with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use logic_world_save::{parse_save_bytes, PegType, SaveFile, Version, Writer};
+
+/// A chain of `count` inverters, each wired to the next, with every other output on.
+fn generate(count: usize) -> SaveFile {
+    let mut save = SaveFile::empty(Version(0, 91, 3, 0));
+    let mut previous = None;
+    for i in 0..count {
+        let address = save
+            .build_component("MHG.Inverter")
+            .at(i as i32 * 300, 0, 0)
+            .inputs(1)
+            .outputs(1)
+            .add()
+            .unwrap();
+        if let Some(previous) = previous {
+            let state_id = save
+                .add_wire(
+                    (previous, PegType::Output, 0),
+                    (address.0, PegType::Input, 0),
+                )
+                .unwrap();
+            save.set_state(state_id, i % 2 == 0).unwrap();
+        }
+        previous = Some(address.0);
+    }
+    save
+}
+
+fn parse_and_write(c: &mut Criterion) {
+    let save = generate(200_000);
+    let bytes = Writer::new().write(&save).unwrap();
+
+    let mut group = c.benchmark_group("200k components");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.sample_size(10);
+    group.bench_function("write", |b| b.iter(|| Writer::new().write(&save).unwrap()));
+    group.bench_function("parse", |b| b.iter(|| parse_save_bytes(&bytes).unwrap()));
+    group.finish();
+}
+
+/// [`Writer::write`] reserves [`SaveFile::serialized_size_estimate`] up front, a writer over a
+/// plain `Vec` grows it as it goes.
+fn presized_write(c: &mut Criterion) {
+    let save = generate(500_000);
+
+    let mut group = c.benchmark_group("500k components");
+    group.throughput(Throughput::Bytes(save.serialized_size_estimate() as u64));
+    group.sample_size(10);
+    group.bench_function("write presized", |b| {
+        b.iter(|| Writer::new().write(&save).unwrap())
+    });
+    group.bench_function("write growing", |b| {
+        b.iter(|| {
+            let mut writer = Writer::from_writer(Vec::new());
+            writer.write_to(&save).unwrap();
+            writer.into_inner()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_and_write, presized_write);
+criterion_main!(benches);
@@ -0,0 +1,101 @@
+//! Changes one byte of a valid save, parsing has to either fail cleanly or give a save that
+//! round-trips through the writer.
+//!
+//! Run with `cargo +nightly fuzz run mutate_save` from the repository root.
+
+#![no_main]
+
+use std::sync::OnceLock;
+
+use libfuzzer_sys::fuzz_target;
+use logic_world_save::{
+    parse_save_bytes,
+    CircuitBuilder,
+    Color,
+    CustomData,
+    Parser,
+    PegAddress,
+    PegType,
+    Quat,
+    Vec3,
+    Version,
+    Writer,
+};
+
+fn valid_save() -> &'static [u8] {
+    static SAVE: OnceLock<Vec<u8>> = OnceLock::new();
+    SAVE.get_or_init(|| {
+        let mut builder = CircuitBuilder::new();
+        let board = builder
+            .add_component(
+                "MHG.CircuitBoard",
+                Vec3::zero(),
+                Quat::IDENTITY,
+                CustomData::Unknown(vec![0; 12]),
+            )
+            .expect("known component");
+        let switch = builder
+            .add_component(
+                "MHG.Switch",
+                Vec3 { x: 1, y: 0, z: 0 },
+                Quat::IDENTITY,
+                CustomData::Switch {
+                    color: Color::from_rgb(255, 0, 0),
+                    on: true,
+                },
+            )
+            .expect("known component");
+        let inverter = builder
+            .add_component(
+                "MHG.Inverter",
+                Vec3 { x: 2, y: 0, z: 0 },
+                Quat::IDENTITY,
+                CustomData::None,
+            )
+            .expect("known component");
+        builder
+            .connect(
+                PegAddress {
+                    type_: PegType::Output,
+                    component: switch,
+                    index: 0,
+                },
+                PegAddress {
+                    type_: PegType::Input,
+                    component: inverter,
+                    index: 0,
+                },
+            )
+            .expect("pegs exist");
+        builder.set_parent(switch, board).expect("board exists");
+        builder.set_parent(inverter, board).expect("board exists");
+        builder
+            .build(Version(0, 91, 0, 0))
+            .to_bytes()
+            .expect("built save is valid")
+    })
+}
+
+fuzz_target!(|mutation: (usize, u8)| {
+    let (position, value) = mutation;
+    let mut bytes = valid_save().to_vec();
+    let len = bytes.len();
+    bytes[position % len] = value;
+    let Ok(save) = Parser::from_bytes(&bytes).parse_save() else {
+        return;
+    };
+    // Whatever the parser accepted has to survive being written and parsed again. Compared as
+    // bytes since a mutated float can be NaN.
+    let Ok(written) = Writer::new().without_validation().write(&save) else {
+        return;
+    };
+    let reparsed = parse_save_bytes(&written).expect("written save parses");
+    let rewritten = Writer::new()
+        .without_validation()
+        .write(&reparsed)
+        .expect("reparsed save writes");
+    assert!(
+        rewritten == written,
+        "rewriting a parsed save changed its bytes"
+    );
+});
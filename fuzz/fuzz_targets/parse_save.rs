@@ -0,0 +1,12 @@
+//! Feeds arbitrary bytes to the parser, which has to return an error instead of panicking.
+//!
+//! Run with `cargo +nightly fuzz run parse_save` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logic_world_save::Parser;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Parser::from_bytes(data).parse_save();
+});
@@ -0,0 +1,40 @@
+//! Async wrappers for use inside a Tokio runtime.
+//!
+//! The format is read and written in one go, so these only make the file access async and leave
+//! the parsing and writing to the synchronous code.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+use crate::parse::Parser;
+use crate::save::SaveFile;
+
+pub async fn parse_save_async(path: impl AsRef<Path>) -> Result<SaveFile> {
+    let path = path.as_ref();
+    let mut bytes = Vec::new();
+    File::open(path)
+        .await
+        .with_context(|| format!("opening {}", path.display()))?
+        .read_to_end(&mut bytes)
+        .await
+        .with_context(|| format!("reading {}", path.display()))?;
+    let save = Parser::from_bytes(&bytes)
+        .parse_save()
+        .with_context(|| format!("parsing {}", path.display()))?;
+    Ok(save)
+}
+
+/// Serializes the whole save before touching `path`, like [`SaveFile::save`].
+pub async fn write_save_async(save: SaveFile, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let bytes = save
+        .to_bytes()
+        .with_context(|| format!("serializing {}", path.display()))?;
+    tokio::fs::write(path, bytes)
+        .await
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use anyhow::{bail, Result};
+
+use crate::save::SaveFile;
+use crate::types::{
+    Address,
+    Color,
+    Component,
+    CustomData,
+    PegAddress,
+    PegType,
+    Quat,
+    StateId,
+    Vec3,
+    Version,
+    Wire,
+};
+
+/// Builds a single component, created with [`SaveFile::build_component`].
+pub struct ComponentBuilder<'a> {
+    save: &'a mut SaveFile,
+    id: Arc<str>,
+    parent: Address,
+    position: Vec3,
+    rotation: Quat,
+    inputs: usize,
+    outputs: usize,
+    custom_data: CustomData,
+}
+
+/// Builds a whole circuit from scratch, handing out addresses and state ids as it goes.
+///
+/// Unlike editing a [`SaveFile`] directly every wire and parent is checked when it is added, so
+/// the finished save passes [`SaveFile::validate`].
+pub struct CircuitBuilder {
+    save: SaveFile,
+}
+
+impl Default for CircuitBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBuilder {
+    pub fn new() -> Self {
+        Self {
+            save: SaveFile::empty(Version(0, 0, 0, 0)),
+        }
+    }
+
+    /// Adds a component with the pegs the game gives `type_id`, use
+    /// [`CircuitBuilder::add_component_with_pegs`] for types this crate doesn't know.
+    ///
+    /// Fails if the peg counts of `type_id` aren't known.
+    pub fn add_component(
+        &mut self,
+        type_id: &str,
+        position: Vec3,
+        rotation: Quat,
+        custom_data: CustomData,
+    ) -> Result<Address> {
+        let Some((inputs, outputs)) = vanilla_peg_counts(type_id) else {
+            bail!("Unknown peg counts for component {type_id}, use add_component_with_pegs");
+        };
+        Ok(self.add_component_with_pegs(type_id, position, rotation, custom_data, inputs, outputs))
+    }
+
+    pub fn add_component_with_pegs(
+        &mut self,
+        type_id: &str,
+        position: Vec3,
+        rotation: Quat,
+        custom_data: CustomData,
+        inputs: usize,
+        outputs: usize,
+    ) -> Address {
+        let save = &mut self.save;
+        save.comp_map.ensure(type_id);
+        let address = save.get_free_address();
+        let inputs = (0..inputs).map(|_| save.get_free_state_id()).collect();
+        let outputs: Vec<_> = (0..outputs).map(|_| save.get_free_state_id()).collect();
+        set_switch_output(save, &custom_data, &outputs);
+        save.push_component(Component {
+            address,
+            parent: Address::ROOT,
+            id: type_id.into(),
+            position,
+            rotation,
+            inputs,
+            outputs,
+            custom_data,
+        });
+        address
+    }
+
+    /// Wires an output peg to an input peg, in either order.
+    pub fn connect(&mut self, from: PegAddress, to: PegAddress) -> Result<StateId> {
+        self.save.add_wire(from, to)
+    }
+
+    /// Places `child` on `parent`, refusing anything that would make a parent cycle.
+    pub fn set_parent(&mut self, child: Address, parent: Address) -> Result<()> {
+        self.save.set_parent(child, parent)
+    }
+
+    pub fn build(mut self, game_version: Version) -> SaveFile {
+        self.save.game_version = game_version;
+        self.save.rebuild_index();
+        self.save
+    }
+}
+
+/// Turns on the output state of a switch that starts on, like [`SaveFile::set_switch_on`].
+fn set_switch_output(save: &mut SaveFile, custom_data: &CustomData, outputs: &[StateId]) {
+    if let (CustomData::Switch { on: true, .. }, Some(&output)) = (custom_data, outputs.first()) {
+        save.states.set(output, true);
+    }
+}
+
+/// Input and output peg counts of the built in components the builder knows about.
+fn vanilla_peg_counts(type_id: &str) -> Option<(usize, usize)> {
+    Some(match type_id {
+        "MHG.CircuitBoard" => (0, 0),
+        "MHG.Peg" => (1, 0),
+        "MHG.Switch" | "MHG.Button" => (0, 1),
+        "MHG.Inverter" | "MHG.Buffer" | "MHG.Delayer" => (1, 1),
+        "MHG.AndGate" | "MHG.XorGate" => (2, 1),
+        _ => return None,
+    })
+}
+
+impl SaveFile {
+    /// Starts building a component of type `id`, see [`ComponentBuilder::add`].
+    pub fn build_component(&mut self, id: &str) -> ComponentBuilder<'_> {
+        ComponentBuilder {
+            save: self,
+            id: id.into(),
+            parent: Address::ROOT,
+            position: Vec3::zero(),
+            rotation: Quat::IDENTITY,
+            inputs: 0,
+            outputs: 0,
+            custom_data: CustomData::Unknown(Vec::new()),
+        }
+    }
+
+    /// Adds a component in one call, placed on `parent` or in the world for [`Address::ROOT`].
+    ///
+    /// Fails like [`ComponentBuilder::add`] does, including when `parent` doesn't exist.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_component(
+        &mut self,
+        id: &str,
+        parent: Address,
+        position: Vec3,
+        rotation: Quat,
+        inputs: usize,
+        outputs: usize,
+        custom_data: CustomData,
+    ) -> Result<Address> {
+        self.build_component(id)
+            .parent(parent)
+            .position(position)
+            .rotation(rotation)
+            .inputs(inputs)
+            .outputs(outputs)
+            .custom_data(custom_data)
+            .add()
+    }
+
+    /// Connects an output peg to an input peg, in either order, see [`SaveFile::add_wire_any`]
+    /// for other combinations.
+    ///
+    /// Pegs can be given as `(address, type, index)` tuples. The wire joins the state id of its
+    /// input peg, which is returned.
+    pub fn add_wire(
+        &mut self,
+        start: impl Into<PegAddress>,
+        end: impl Into<PegAddress>,
+    ) -> Result<StateId> {
+        self.add_wire_with_rotation(start.into(), end.into(), 0.)
+    }
+
+    /// Like [`SaveFile::add_wire`], but also allows wiring two inputs or two outputs together.
+    ///
+    /// Wiring two inputs merges the state id of `end`'s input into `start`'s, a wire between two
+    /// outputs gets a fresh state id.
+    pub fn add_wire_any(
+        &mut self,
+        start: impl Into<PegAddress>,
+        end: impl Into<PegAddress>,
+    ) -> Result<StateId> {
+        self.add_wire_any_with_rotation(start.into(), end.into(), 0.)
+    }
+
+    pub fn add_wire_with_rotation(
+        &mut self,
+        start: PegAddress,
+        end: PegAddress,
+        rotation: f32,
+    ) -> Result<StateId> {
+        if start.type_ == end.type_ {
+            bail!("Can not connect two {} pegs", start.type_);
+        }
+        self.add_wire_any_with_rotation(start, end, rotation)
+    }
+
+    pub fn add_wire_any_with_rotation(
+        &mut self,
+        start: PegAddress,
+        end: PegAddress,
+        rotation: f32,
+    ) -> Result<StateId> {
+        let start_state = self.peg_state(&start)?;
+        let end_state = self.peg_state(&end)?;
+
+        let state_id = match (start.type_, end.type_) {
+            (PegType::Input, PegType::Input) => {
+                self.merge_input_states(end_state, start_state);
+                start_state
+            }
+            (PegType::Input, PegType::Output) => start_state,
+            (PegType::Output, PegType::Input) => end_state,
+            (PegType::Output, PegType::Output) => self.get_free_state_id(),
+        };
+        self.push_wire(Wire {
+            start,
+            end,
+            state_id,
+            rotation,
+        });
+        Ok(state_id)
+    }
+
+    /// The state id of the peg, failing if the component or the peg doesn't exist.
+    fn peg_state(&self, peg: &PegAddress) -> Result<StateId> {
+        let Some(comp) = self.find_component(peg.component) else {
+            bail!("Wire references missing component {}", peg.component);
+        };
+        let pegs = match peg.type_ {
+            PegType::Input => &comp.inputs,
+            PegType::Output => &comp.outputs,
+        };
+        match usize::try_from(peg.index)
+            .ok()
+            .and_then(|index| pegs.get(index))
+        {
+            Some(&state_id) => Ok(state_id),
+            None => bail!(
+                "Component {} has no {} peg {}",
+                comp.address,
+                peg.type_,
+                peg.index
+            ),
+        }
+    }
+
+    /// Moves every input and wire on state id `from` over to `to`.
+    fn merge_input_states(&mut self, from: StateId, to: StateId) {
+        if from == to {
+            return;
+        }
+        for comp in &mut self.components {
+            for state_id in comp.inputs.iter_mut().filter(|id| **id == from) {
+                *state_id = to;
+            }
+        }
+        for wire in self.wires.iter_mut().filter(|wire| wire.state_id == from) {
+            wire.state_id = to;
+        }
+    }
+}
+
+impl ComponentBuilder<'_> {
+    pub fn at(mut self, x: i32, y: i32, z: i32) -> Self {
+        self.position = Vec3 { x, y, z };
+        self
+    }
+
+    pub fn position(mut self, position: Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn parent(mut self, parent: Address) -> Self {
+        self.parent = parent;
+        self
+    }
+
+    pub fn inputs(mut self, count: usize) -> Self {
+        self.inputs = count;
+        self
+    }
+
+    pub fn outputs(mut self, count: usize) -> Self {
+        self.outputs = count;
+        self
+    }
+
+    pub fn custom_data(mut self, custom_data: CustomData) -> Self {
+        self.custom_data = custom_data;
+        self
+    }
+
+    pub fn switch_color(mut self, r: u8, g: u8, b: u8) -> Self {
+        let on = matches!(self.custom_data, CustomData::Switch { on: true, .. });
+        self.custom_data = CustomData::Switch {
+            color: Color::from_rgb(r, g, b),
+            on,
+        };
+        self
+    }
+
+    pub fn switch_on(mut self, on: bool) -> Self {
+        let color = match self.custom_data {
+            CustomData::Switch { color, .. } => color,
+            _ => Color::from_rgb(0, 0, 0),
+        };
+        self.custom_data = CustomData::Switch { color, on };
+        self
+    }
+
+    /// Registers the component type, allocates an address and one state id per peg, and adds
+    /// the component to the save.
+    ///
+    /// Fails if the parent doesn't exist or the custom data doesn't fit the component type.
+    pub fn add(self) -> Result<Address> {
+        if !self.custom_data.fits_component(&self.id) {
+            bail!(
+                "Custom data {:?} can not be used for component {}",
+                self.custom_data,
+                self.id
+            );
+        }
+        if self.parent != Address::ROOT && self.save.find_component(self.parent).is_none() {
+            bail!("No component with address {} to place on", self.parent);
+        }
+
+        let save = self.save;
+        save.comp_map.ensure(&self.id);
+        let address = save.get_free_address();
+        let inputs = (0..self.inputs).map(|_| save.get_free_state_id()).collect();
+        let outputs: Vec<_> = (0..self.outputs)
+            .map(|_| save.get_free_state_id())
+            .collect();
+        set_switch_output(save, &self.custom_data, &outputs);
+
+        save.push_component(Component {
+            address,
+            parent: self.parent,
+            id: self.id,
+            position: self.position,
+            rotation: self.rotation,
+            inputs,
+            outputs,
+            custom_data: self.custom_data,
+        });
+
+        Ok(address)
+    }
+}
@@ -0,0 +1,93 @@
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Address, StateId, States};
+
+impl SaveFile {
+    /// Renumbers state ids from 1 in order of first use and shrinks the states buffer to fit,
+    /// returning the old to new mapping.
+    ///
+    /// Ids of 0 and below don't point into the states buffer and are kept as they are.
+    pub fn compact_state_ids(&mut self) -> HashMap<StateId, StateId> {
+        let mut mapping = HashMap::new();
+        let mut states = States(vec![0]);
+        let mut next = StateId(0);
+        let mut rebase = |id: StateId| {
+            if id.0 <= 0 {
+                return id;
+            }
+            *mapping.entry(id).or_insert_with(|| {
+                next.0 += 1;
+                states.set(next, self.states.get_bit(id));
+                next
+            })
+        };
+
+        for comp in &mut self.components {
+            for state_id in comp.inputs.iter_mut().chain(comp.outputs.iter_mut()) {
+                *state_id = rebase(*state_id);
+            }
+        }
+        for wire in &mut self.wires {
+            wire.state_id = rebase(wire.state_id);
+        }
+
+        self.states = states;
+        self.highest_state_id = next;
+        self.free_state_ids.clear();
+        mapping
+    }
+
+    /// Renumbers components from 1 in their current order and rebuilds the index, returning the
+    /// old to new mapping.
+    ///
+    /// Parents and wire ends pointing at missing components get addresses past the components
+    /// instead of colliding with one of them.
+    pub fn compact_addresses(&mut self) -> HashMap<Address, Address> {
+        let mut mapping = HashMap::with_capacity(self.components.len());
+        let mut next = Address::ROOT;
+        let mut rebase = |address: Address| {
+            if address == Address::ROOT {
+                return address;
+            }
+            *mapping.entry(address).or_insert_with(|| {
+                next.0 += 1;
+                next
+            })
+        };
+
+        for comp in &mut self.components {
+            comp.address = rebase(comp.address);
+        }
+        for comp in &mut self.components {
+            comp.parent = rebase(comp.parent);
+        }
+        for wire in &mut self.wires {
+            wire.start.component = rebase(wire.start.component);
+            wire.end.component = rebase(wire.end.component);
+        }
+
+        self.highest_address = next.max(Address(1));
+        self.rebuild_index();
+        mapping
+    }
+
+    /// Drops component map entries no component uses, keeping the ids of the rest, and returns
+    /// how many were dropped.
+    pub fn prune_comp_map(&mut self) -> usize {
+        let used: HashSet<&str> = self.components.iter().map(|comp| &*comp.id).collect();
+        let unused: Vec<Arc<str>> = self
+            .comp_map
+            .iter()
+            .filter(|(_, name)| !used.contains(name))
+            .map(|(_, name)| name.into())
+            .collect();
+        for name in &unused {
+            self.comp_map.remove(name);
+        }
+        unused.len()
+    }
+}
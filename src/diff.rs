@@ -0,0 +1,237 @@
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Address, Component, CustomData, PegAddress, Quat, StateId, Vec3, Wire};
+
+/// Changes that turn one save into another, see [`SaveFile::diff`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SaveDiff {
+    pub added_components: Vec<Component>,
+    pub removed_components: Vec<Address>,
+    pub modified_components: Vec<(Address, ComponentDiff)>,
+    pub added_wires: Vec<Wire>,
+    pub removed_wires: Vec<WireKey>,
+    /// Wires that only changed their rotation, with the new rotation.
+    pub rotated_wires: Vec<(WireKey, f32)>,
+    /// State ids that are on in one save and off in the other, with the new value.
+    pub changed_states: Vec<(StateId, bool)>,
+}
+
+/// New values of the fields that changed, `None` for those that didn't.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ComponentDiff {
+    pub position: Option<Vec3>,
+    pub rotation: Option<Quat>,
+    pub parent: Option<Address>,
+    pub custom_data: Option<CustomData>,
+}
+
+/// Identifies a wire by the pegs it connects.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WireKey {
+    pub start: PegAddress,
+    pub end: PegAddress,
+}
+
+impl WireKey {
+    pub fn of(wire: &Wire) -> Self {
+        Self {
+            start: wire.start.clone(),
+            end: wire.end.clone(),
+        }
+    }
+}
+
+impl ComponentDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl SaveDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_components.is_empty()
+            && self.removed_components.is_empty()
+            && self.modified_components.is_empty()
+            && self.added_wires.is_empty()
+            && self.removed_wires.is_empty()
+            && self.rotated_wires.is_empty()
+            && self.changed_states.is_empty()
+    }
+
+    /// Patches `base` with the changes, failing if it doesn't contain what the diff expects.
+    pub fn apply(self, base: &mut SaveFile) -> Result<()> {
+        let mut removed: HashSet<WireKey> = self.removed_wires.into_iter().collect();
+        base.wires
+            .retain(|wire| removed.is_empty() || !removed.remove(&WireKey::of(wire)));
+        if let Some(key) = removed.into_iter().next() {
+            bail!("No wire from {:?} to {:?}", key.start, key.end);
+        }
+
+        let mut rotated: HashMap<WireKey, f32> = self.rotated_wires.into_iter().collect();
+        for wire in &mut base.wires {
+            if let Some(rotation) = rotated.remove(&WireKey::of(wire)) {
+                wire.rotation = rotation;
+            }
+        }
+        if let Some((key, _)) = rotated.into_iter().next() {
+            bail!("No wire from {:?} to {:?}", key.start, key.end);
+        }
+
+        // Wires of removed components are listed in the diff themselves, so unlike
+        // `remove_component` this leaves wires alone.
+        let mut removed: HashSet<Address> = self.removed_components.into_iter().collect();
+        base.components
+            .retain(|comp| removed.is_empty() || !removed.remove(&comp.address));
+        if let Some(address) = removed.into_iter().next() {
+            bail!("No component with address {address}");
+        }
+        base.rebuild_index();
+
+        for (address, changes) in self.modified_components {
+            let comp = base
+                .find_component_mut(address)
+                .ok_or_else(|| anyhow!("No component with address {address}"))?;
+            if let Some(position) = changes.position {
+                comp.position = position;
+            }
+            if let Some(rotation) = changes.rotation {
+                comp.rotation = rotation;
+            }
+            if let Some(parent) = changes.parent {
+                comp.parent = parent;
+            }
+            if let Some(custom_data) = changes.custom_data {
+                comp.custom_data = custom_data;
+            }
+        }
+        base.rebuild_index();
+
+        for comp in self.added_components {
+            if base.find_component(comp.address).is_some() {
+                bail!("Component {} already exists", comp.address);
+            }
+            base.comp_map.ensure(&comp.id);
+            base.highest_address = base.highest_address.max(comp.address);
+            for &state_id in comp.inputs.iter().chain(&comp.outputs) {
+                base.reserve_state(state_id);
+            }
+            base.push_component(comp);
+        }
+
+        for wire in self.added_wires {
+            base.reserve_state(wire.state_id);
+            base.push_wire(wire);
+        }
+
+        for (state_id, on) in self.changed_states {
+            if state_id.0 < 0 {
+                bail!("State id {state_id} is negative");
+            }
+            base.states.set(state_id, on);
+        }
+
+        Ok(())
+    }
+}
+
+impl SaveFile {
+    /// What changed from `self` to `other`, matching components by address and wires by the
+    /// pegs they connect and their state id.
+    ///
+    /// A component whose type or pegs changed is reported as removed and added again, as is a
+    /// wire whose state id changed. States are compared bit by bit, the length of the states
+    /// buffer is not part of the diff.
+    pub fn diff(&self, other: &SaveFile) -> SaveDiff {
+        let mut diff = SaveDiff::default();
+
+        let ours: HashMap<Address, &Component> =
+            self.components.iter().map(|c| (c.address, c)).collect();
+        let theirs: HashMap<Address, &Component> =
+            other.components.iter().map(|c| (c.address, c)).collect();
+
+        for comp in &self.components {
+            match theirs.get(&comp.address) {
+                None => diff.removed_components.push(comp.address),
+                Some(new)
+                    if new.id != comp.id
+                        || new.inputs != comp.inputs
+                        || new.outputs != comp.outputs =>
+                {
+                    diff.removed_components.push(comp.address);
+                    diff.added_components.push((*new).clone());
+                }
+                Some(new) => {
+                    let changes = ComponentDiff {
+                        position: (new.position != comp.position).then_some(new.position),
+                        rotation: (new.rotation != comp.rotation).then_some(new.rotation),
+                        parent: (new.parent != comp.parent).then_some(new.parent),
+                        custom_data: (new.custom_data != comp.custom_data)
+                            .then(|| new.custom_data.clone()),
+                    };
+                    if !changes.is_empty() {
+                        diff.modified_components.push((comp.address, changes));
+                    }
+                }
+            }
+        }
+        for comp in &other.components {
+            if !ours.contains_key(&comp.address) {
+                diff.added_components.push(comp.clone());
+            }
+        }
+
+        let our_wires: HashSet<(WireKey, StateId)> = self
+            .wires
+            .iter()
+            .map(|wire| (WireKey::of(wire), wire.state_id))
+            .collect();
+        let their_wires: HashMap<(WireKey, StateId), f32> = other
+            .wires
+            .iter()
+            .map(|wire| ((WireKey::of(wire), wire.state_id), wire.rotation))
+            .collect();
+        for wire in &self.wires {
+            match their_wires.get(&(WireKey::of(wire), wire.state_id)) {
+                None => diff.removed_wires.push(WireKey::of(wire)),
+                Some(&rotation) if (rotation - wire.rotation).abs() >= f32::EPSILON => {
+                    diff.rotated_wires.push((WireKey::of(wire), rotation));
+                }
+                Some(_) => {}
+            }
+        }
+        for wire in &other.wires {
+            if !our_wires.contains(&(WireKey::of(wire), wire.state_id)) {
+                diff.added_wires.push(wire.clone());
+            }
+        }
+
+        let bits = self.states.len_bits().max(other.states.len_bits());
+        for id in (0..bits)
+            .map_while(|id| i32::try_from(id).ok())
+            .map(StateId)
+        {
+            let on = other.states.get_bit(id);
+            if self.states.get_bit(id) != on {
+                diff.changed_states.push((id, on));
+            }
+        }
+
+        diff
+    }
+
+    /// Makes sure `state_id` is never handed out as a free state id and is inside the states
+    /// buffer.
+    fn reserve_state(&mut self, state_id: StateId) {
+        if state_id.0 < 0 {
+            return;
+        }
+        self.highest_state_id = self.highest_state_id.max(state_id);
+        self.free_state_ids.retain(|&id| id != state_id);
+        let bit = self.states.get_bit(state_id);
+        self.states.set(state_id, bit);
+    }
+}
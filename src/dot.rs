@@ -0,0 +1,137 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::save::SaveFile;
+use crate::types::{Address, Component, PegAddress, PegType};
+
+impl SaveFile {
+    /// Renders the circuit as a Graphviz digraph, pegs become ports on record shaped nodes and
+    /// a component with children is drawn inside a cluster together with them, nested as deep as
+    /// the components are.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        self.write_dot(&mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_dot(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "digraph circuit {{")?;
+        writeln!(out, "    rankdir=LR;")?;
+        writeln!(out, "    node [shape=record];")?;
+
+        let mut clusters = Clusters {
+            children: BTreeMap::new(),
+            written: BTreeSet::new(),
+        };
+        for comp in &self.components {
+            clusters.children.entry(comp.parent).or_default().push(comp);
+        }
+        clusters.write_children(out, Address::ROOT, 1)?;
+        // Children of a parent that isn't in the save get a cluster of their own.
+        let missing: Vec<Address> = clusters
+            .children
+            .keys()
+            .copied()
+            .filter(|&parent| parent != Address::ROOT && self.find_component(parent).is_none())
+            .collect();
+        for parent in missing {
+            writeln!(out, "    subgraph cluster_{} {{", parent.0)?;
+            writeln!(out, "        label=\"#{parent}\";")?;
+            clusters.write_children(out, parent, 2)?;
+            writeln!(out, "    }}")?;
+        }
+        // Whatever is left is part of a parent cycle, never reached from the root.
+        for comp in &self.components {
+            clusters.write_component(out, comp, 1)?;
+        }
+
+        for wire in &self.wires {
+            writeln!(
+                out,
+                "    {} -> {} [label=\"{}\"];",
+                Port(&wire.start),
+                Port(&wire.end),
+                wire.state_id
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+struct Clusters<'a> {
+    children: BTreeMap<Address, Vec<&'a Component>>,
+    written: BTreeSet<Address>,
+}
+
+impl<'a> Clusters<'a> {
+    fn write_children(&mut self, out: &mut String, parent: Address, depth: usize) -> fmt::Result {
+        let children = self.children.get(&parent).cloned().unwrap_or_default();
+        for comp in children {
+            self.write_component(out, comp, depth)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `comp` once, inside a cluster with its children if it has any.
+    fn write_component(
+        &mut self,
+        out: &mut String,
+        comp: &'a Component,
+        depth: usize,
+    ) -> fmt::Result {
+        if !self.written.insert(comp.address) {
+            return Ok(());
+        }
+        let indent = "    ".repeat(depth);
+        if !self.children.contains_key(&comp.address) {
+            return write_node(out, comp, &indent);
+        }
+        writeln!(out, "{indent}subgraph cluster_{} {{", comp.address.0)?;
+        writeln!(out, "{indent}    label=\"#{}\";", comp.address)?;
+        write_node(out, comp, &format!("{indent}    "))?;
+        self.write_children(out, comp.address, depth + 1)?;
+        writeln!(out, "{indent}}}")
+    }
+}
+
+fn write_node(out: &mut String, comp: &Component, indent: &str) -> fmt::Result {
+    let ports = |prefix: &str, count: usize| {
+        (0..count)
+            .map(|index| format!("<{prefix}{index}> {index}"))
+            .collect::<Vec<_>>()
+            .join("|")
+    };
+    write!(out, "{indent}c{} [label=\"{{{{", comp.address.0)?;
+    out.push_str(&ports("i", comp.inputs.len()));
+    write!(out, "}}|")?;
+    for c in comp.id.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    write!(out, " #{}|{{", comp.address)?;
+    out.push_str(&ports("o", comp.outputs.len()));
+    writeln!(out, "}}}}\"];")
+}
+
+struct Port<'a>(&'a PegAddress);
+
+impl fmt::Display for Port<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self.0.type_ {
+            PegType::Input => "i",
+            PegType::Output => "o",
+        };
+        write!(f, "c{}", self.0.component.0)?;
+        // A negative index has no port to point at, so the edge goes to the node itself.
+        if self.0.index >= 0 {
+            write!(f, ":{prefix}{}", self.0.index)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::types::Address;
+
+#[derive(Debug)]
+pub enum ParseError {
+    /// The input doesn't start like a save, `found` is what it starts with instead.
+    InvalidHeader {
+        found: Vec<u8>,
+    },
+    EmptyFile,
+    UnsupportedFormatVersion(u8),
+    /// Saves from game builds before format version 7 have to be opened and saved again in the
+    /// game to upgrade them.
+    LegacyFormatVersion(u8),
+    InvalidSaveType(u8),
+    UnknownPegType(u8),
+    MissingCompMapEntry(u16),
+    UnexpectedEof,
+    NegativeCount {
+        field: &'static str,
+        count: i32,
+    },
+    CountTooLarge {
+        field: &'static str,
+        count: usize,
+        max: usize,
+    },
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    UnexpectedFooter {
+        found: Vec<u8>,
+    },
+    /// The input ended `missing` bytes before the end of the footer.
+    MissingFooter {
+        missing: usize,
+    },
+    TrailingBytes(u64),
+    DanglingWire(Address),
+    /// The component reuses the address of component #`first`, which starts at `first_offset`.
+    DuplicateAddress {
+        address: Address,
+        first: usize,
+        first_offset: u64,
+    },
+    /// Wraps another error with where in the file it happened.
+    At {
+        section: Section,
+        offset: u64,
+        context: Hexdump,
+        source: Box<ParseError>,
+    },
+}
+
+/// The bytes around a parse failure, displayed as a hexdump with a caret under the start of the
+/// value that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hexdump {
+    pub start: u64,
+    pub bytes: Vec<u8>,
+    pub caret: u64,
+}
+
+impl fmt::Display for Hexdump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = self.start + self.bytes.len() as u64;
+        for (row, chunk) in self.bytes.chunks(16).enumerate() {
+            let row_start = self.start + row as u64 * 16;
+            write!(f, "\n{row_start:08X} ")?;
+            for byte in chunk {
+                write!(f, " {byte:02X}")?;
+            }
+            let row_end = row_start + chunk.len() as u64;
+            let at_end = self.caret == end && row_end == end;
+            if (row_start..row_end).contains(&self.caret) || at_end {
+                let column = (self.caret - row_start) as usize;
+                write!(f, "\n{:width$}^^", "", width = 10 + column * 3)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Part of the save file the parser was working on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    ModVersions,
+    CompMap,
+    Component(usize),
+    Wire(usize),
+    States,
+    Footer,
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header => write!(f, "header"),
+            Self::ModVersions => write!(f, "mod versions"),
+            Self::CompMap => write!(f, "component map"),
+            Self::Component(index) => write!(f, "component #{index}"),
+            Self::Wire(index) => write!(f, "wire #{index}"),
+            Self::States => write!(f, "states"),
+            Self::Footer => write!(f, "footer"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader { found } => {
+                write!(
+                    f,
+                    "Not a Logic World save, starts with '{}'",
+                    found.escape_ascii()
+                )?;
+                if let Some(kind) = guess_file_kind(found) {
+                    write!(f, ", looks like {kind}")?;
+                }
+                Ok(())
+            }
+            Self::EmptyFile => write!(f, "The file is empty"),
+            Self::UnsupportedFormatVersion(version) => {
+                write!(f, "Invalid save format version {version}")
+            }
+            Self::LegacyFormatVersion(version) => write!(
+                f,
+                "Legacy save format version {version}, re-save it in the game to upgrade it"
+            ),
+            Self::InvalidSaveType(save_type) => write!(f, "Invalid save type {save_type}"),
+            Self::UnknownPegType(type_) => write!(f, "Invalid peg type {type_}"),
+            Self::MissingCompMapEntry(id) => write!(f, "Missing id {id} in mapping"),
+            Self::UnexpectedEof => write!(f, "Unexpected end of file"),
+            Self::NegativeCount { field, count } => write!(f, "Negative {field} {count}"),
+            Self::CountTooLarge { field, count, max } => {
+                write!(f, "Too large {field} {count}, maximum is {max}")
+            }
+            #[cfg(feature = "std")]
+            Self::Io(_) => write!(f, "Failed to read save"),
+            Self::UnexpectedFooter { found } => {
+                write!(f, "Invalid footer, '{}'", found.escape_ascii())
+            }
+            Self::MissingFooter { missing } => {
+                write!(f, "File ended {missing} bytes early, footer missing")
+            }
+            Self::TrailingBytes(amount) => write!(f, "Found {amount} bytes after the footer"),
+            Self::DuplicateAddress {
+                address,
+                first,
+                first_offset,
+            } => write!(
+                f,
+                "Address {address} is already used by component #{first} at byte {first_offset:#X}"
+            ),
+            Self::DanglingWire(address) => {
+                write!(f, "Wire references missing component {address}")
+            }
+            Self::At {
+                section,
+                offset,
+                context,
+                source,
+            } => write!(
+                f,
+                "Failed parsing {section} at byte {offset:#X}: {source}{context}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError {
+    /// `what` is `len` long, more than the format's `i32` lengths can hold.
+    TooLarge { what: &'static str, len: usize },
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { what, len } => {
+                write!(f, "Too large {what} {len}, maximum is {}", i32::MAX)
+            }
+        }
+    }
+}
+
+impl core::error::Error for WriteError {}
+
+/// A problem [`crate::Writer::validate`] finds before any bytes are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteIssue {
+    MissingCompMapEntry { address: Address, id: Arc<str> },
+    DuplicateAddress { address: Address },
+    DanglingWire { wire: usize, address: Address },
+    TooLarge(WriteError),
+}
+
+impl fmt::Display for WriteIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingCompMapEntry { address, id } => {
+                write!(
+                    f,
+                    "Component {address} has type {id} which is not in the component map"
+                )
+            }
+            Self::DuplicateAddress { address } => {
+                write!(f, "Address {address} is used by more than one component")
+            }
+            Self::DanglingWire { wire, address } => {
+                write!(f, "Wire #{wire} references missing component {address}")
+            }
+            Self::TooLarge(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl core::error::Error for WriteIssue {}
+
+/// Best guess at what was opened instead of a save, from its first bytes.
+fn guess_file_kind(start: &[u8]) -> Option<&'static str> {
+    if start.starts_with(&[0x1F, 0x8B]) {
+        Some("gzip compressed data")
+    } else if start.starts_with(b"PK\x03\x04") {
+        Some("a zip archive")
+    } else if start
+        .iter()
+        .all(|byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace())
+    {
+        Some("a text file such as worldinfo.succ")
+    } else {
+        None
+    }
+}
+
+impl core::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            // The wrapped error is already part of the message.
+            Self::At { source, .. } => source.source(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
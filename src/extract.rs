@@ -0,0 +1,126 @@
+use alloc::format;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, Result};
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Address, Component, PegAddress, StateId, Vec3, Wire};
+
+impl SaveFile {
+    /// Copies the components at `addresses` into a new save, together with the wires running
+    /// between them, moved so the lowest corner sits at the origin.
+    ///
+    /// Wires with only one end in the selection are dropped, components whose parent isn't
+    /// selected become top level.
+    pub fn extract_subcircuit(&self, addresses: &[Address]) -> Result<SaveFile> {
+        for &address in addresses {
+            if self.find_component(address).is_none() {
+                return Err(anyhow!("No component with address {address}"));
+            }
+        }
+        let selected: HashSet<Address> = addresses.iter().copied().collect();
+        let comps: Vec<&Component> = self
+            .components
+            .iter()
+            .filter(|comp| selected.contains(&comp.address))
+            .collect();
+
+        let mut out = SaveFile::empty(self.game_version.clone());
+        out.format_version = self.format_version;
+        out.save_type = self.save_type;
+        out.raw_strings = self.raw_strings.clone();
+        for (name, version) in &self.mod_versions {
+            let prefix = format!("{name}.");
+            if comps.iter().any(|comp| comp.id.starts_with(&prefix)) {
+                out.mod_versions.insert(name.clone(), version.clone());
+            }
+        }
+
+        let min = comps
+            .iter()
+            .map(|comp| comp.position)
+            .reduce(|min, p| Vec3 {
+                x: min.x.min(p.x),
+                y: min.y.min(p.y),
+                z: min.z.min(p.z),
+            })
+            .unwrap_or(Vec3::zero());
+
+        let positions = comps
+            .iter()
+            .map(|comp| {
+                comp.position
+                    .checked_sub(&min)
+                    .ok_or_else(|| anyhow!("Moving component {} overflows", comp.address))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut new_addresses = HashMap::with_capacity(comps.len());
+        for comp in &comps {
+            new_addresses.insert(comp.address, out.get_free_address());
+        }
+        let mut new_states = HashMap::new();
+
+        for (comp, position) in comps.into_iter().zip(positions) {
+            out.comp_map.ensure(&comp.id);
+            let mut rebase = |id| self.rebase_state(&mut out, &mut new_states, id);
+            let inputs = comp.inputs.iter().map(|&id| rebase(id)).collect();
+            let outputs = comp.outputs.iter().map(|&id| rebase(id)).collect();
+            out.push_component(Component {
+                address: new_addresses[&comp.address],
+                parent: new_addresses
+                    .get(&comp.parent)
+                    .copied()
+                    .unwrap_or(Address::ROOT),
+                position,
+                inputs,
+                outputs,
+                ..comp.clone()
+            });
+        }
+
+        for wire in &self.wires {
+            let (Some(&start), Some(&end)) = (
+                new_addresses.get(&wire.start.component),
+                new_addresses.get(&wire.end.component),
+            ) else {
+                continue;
+            };
+            let state_id = self.rebase_state(&mut out, &mut new_states, wire.state_id);
+            out.push_wire(Wire {
+                start: PegAddress {
+                    component: start,
+                    ..wire.start.clone()
+                },
+                end: PegAddress {
+                    component: end,
+                    ..wire.end.clone()
+                },
+                state_id,
+                rotation: wire.rotation,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Gives `id` a fresh state id in `out` the first time it is seen, copying its current value.
+    ///
+    /// Ids of 0 and below don't point into the states buffer, like in `compact_state_ids`.
+    fn rebase_state(
+        &self,
+        out: &mut SaveFile,
+        new_states: &mut HashMap<StateId, StateId>,
+        id: StateId,
+    ) -> StateId {
+        if id.0 <= 0 {
+            return id;
+        }
+        *new_states.entry(id).or_insert_with(|| {
+            let new = out.get_free_state_id();
+            out.states.set(new, self.states.get_bit(id));
+            new
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Address, PegType, StateId};
+
+/// A peg of a component, `(component, peg index, input or output)`.
+pub type PegNode = (Address, usize, PegType);
+
+/// The wiring of a save as a graph between pegs, with the wires' state ids as edges.
+///
+/// It is a snapshot, editing the save afterwards doesn't update it.
+#[derive(Debug, Clone, Default)]
+pub struct WireGraph {
+    components: Vec<Address>,
+    outputs: HashMap<Address, usize>,
+    pegs: HashMap<PegNode, Vec<(PegNode, StateId)>>,
+}
+
+impl SaveFile {
+    pub fn wire_graph(&self) -> WireGraph {
+        let mut graph = WireGraph {
+            components: self.components.iter().map(|comp| comp.address).collect(),
+            outputs: self
+                .components
+                .iter()
+                .map(|comp| (comp.address, comp.outputs.len()))
+                .collect(),
+            pegs: HashMap::new(),
+        };
+        for wire in &self.wires {
+            let (Ok(start_index), Ok(end_index)) = (
+                usize::try_from(wire.start.index),
+                usize::try_from(wire.end.index),
+            ) else {
+                continue;
+            };
+            let start = (wire.start.component, start_index, wire.start.type_);
+            let end = (wire.end.component, end_index, wire.end.type_);
+            graph
+                .pegs
+                .entry(start)
+                .or_default()
+                .push((end, wire.state_id));
+            graph
+                .pegs
+                .entry(end)
+                .or_default()
+                .push((start, wire.state_id));
+        }
+        graph
+    }
+}
+
+impl WireGraph {
+    /// The pegs wired directly to `peg`, with the state id of the connecting wire.
+    pub fn neighbours(&self, peg: PegNode) -> &[(PegNode, StateId)] {
+        self.pegs.get(&peg).map_or(&[], Vec::as_slice)
+    }
+
+    /// Number of wires attached to an output peg.
+    pub fn fan_out(&self, address: Address, output_index: usize) -> usize {
+        self.neighbours((address, output_index, PegType::Output))
+            .len()
+    }
+
+    /// Every component a signal leaving `start` can reach, `start` itself only if it feeds back
+    /// into itself.
+    pub fn reachable_from(&self, start: Address) -> HashSet<Address> {
+        let mut reached = HashSet::new();
+        let mut queue = vec![start];
+        while let Some(address) = queue.pop() {
+            for next in self.driven_by(address) {
+                if reached.insert(next) {
+                    queue.push(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Groups of components that are wired together, ignoring signal direction.
+    ///
+    /// A component without wires is a group of its own.
+    pub fn connected_components(&self) -> Vec<HashSet<Address>> {
+        let mut neighbours: HashMap<Address, Vec<Address>> = HashMap::new();
+        for (&(from, ..), edges) in &self.pegs {
+            for &((to, ..), _) in edges {
+                neighbours.entry(from).or_default().push(to);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut groups = Vec::new();
+        for &address in &self.components {
+            if !seen.insert(address) {
+                continue;
+            }
+            let mut group = HashSet::from_iter([address]);
+            let mut queue = vec![address];
+            while let Some(current) = queue.pop() {
+                for &next in neighbours.get(&current).into_iter().flatten() {
+                    if seen.insert(next) {
+                        group.insert(next);
+                        queue.push(next);
+                    }
+                }
+            }
+            groups.push(group);
+        }
+        groups
+    }
+
+    /// Whether some component's output eventually drives one of its own inputs.
+    pub fn has_cycle(&self) -> bool {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Color {
+            Visiting,
+            Done,
+        }
+
+        let mut colors: HashMap<Address, Color> = HashMap::new();
+        for &root in &self.components {
+            if colors.contains_key(&root) {
+                continue;
+            }
+            colors.insert(root, Color::Visiting);
+            let mut stack = vec![(root, self.driven_by(root).into_iter())];
+            while let Some((_, next)) = stack.last_mut() {
+                match next.next() {
+                    Some(child) => match colors.get(&child) {
+                        Some(Color::Visiting) => return true,
+                        Some(Color::Done) => {}
+                        None => {
+                            colors.insert(child, Color::Visiting);
+                            stack.push((child, self.driven_by(child).into_iter()));
+                        }
+                    },
+                    None => {
+                        let (done, _) = stack.pop().expect("stack is not empty");
+                        colors.insert(done, Color::Done);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Components with an input that the outputs of `address` drive, following wires between
+    /// inputs as well.
+    fn driven_by(&self, address: Address) -> HashSet<Address> {
+        let output_count = self.outputs.get(&address).copied().unwrap_or(0);
+        let mut seen: HashSet<PegNode> = (0..output_count)
+            .map(|index| (address, index, PegType::Output))
+            .collect();
+        let mut queue: Vec<PegNode> = seen.iter().copied().collect();
+        let mut driven = HashSet::new();
+        while let Some(peg) = queue.pop() {
+            for &(next, _) in self.neighbours(peg) {
+                if next.2 == PegType::Input && seen.insert(next) {
+                    driven.insert(next.0);
+                    queue.push(next);
+                }
+            }
+        }
+        driven
+    }
+}
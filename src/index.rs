@@ -0,0 +1,518 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::{bail, Result};
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Address, Component, PegAddress, StateId, Wire};
+
+/// What happens to the components placed on a component that is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildPolicy {
+    /// Remove them as well, and everything placed on them.
+    Remove,
+    /// Keep them as top level components.
+    MoveToRoot,
+}
+
+/// Everything [`SaveFile::remove_component`] took out of the save.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemovedComponent {
+    pub component: Component,
+    /// Components removed because they were placed on it, empty with [`ChildPolicy::MoveToRoot`].
+    pub descendants: Vec<Component>,
+    /// Wires with an end on the component or one of its descendants.
+    pub wires: Vec<Wire>,
+}
+
+/// Lookup tables derived from the components of a save.
+///
+/// Entries are verified on use, so code that edits `SaveFile::components` directly only makes
+/// lookups slower until [`SaveFile::rebuild_index`] is called, never wrong.
+///
+/// Wires and parents are only indexed by the editing methods, wires pushed onto `SaveFile::wires`
+/// or components reparented without [`SaveFile::set_parent`] are not found by
+/// [`SaveFile::wires_for_component`] and [`SaveFile::children_of`] until the index is rebuilt.
+#[derive(Clone, Default)]
+pub(crate) struct Index {
+    by_address: HashMap<Address, usize>,
+    wires_by_address: HashMap<Address, Vec<usize>>,
+    children: HashMap<Address, Vec<usize>>,
+    /// How many components and wires were indexed, to notice ones added or removed directly.
+    components: usize,
+    wires: usize,
+}
+
+impl core::fmt::Debug for Index {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "[...]")
+    }
+}
+
+impl Index {
+    pub(crate) fn build(components: &[Component], wires: &[Wire]) -> Self {
+        let mut index = Self::default();
+        for (comp_index, comp) in components.iter().enumerate() {
+            index.insert_component(comp, comp_index);
+        }
+        for (wire_index, wire) in wires.iter().enumerate() {
+            index.insert_wire(wire, wire_index);
+        }
+        index
+    }
+
+    fn insert_component(&mut self, comp: &Component, comp_index: usize) {
+        self.by_address.entry(comp.address).or_insert(comp_index);
+        self.children
+            .entry(comp.parent)
+            .or_default()
+            .push(comp_index);
+        self.components = self.components.max(comp_index + 1);
+    }
+
+    fn insert_wire(&mut self, wire: &Wire, wire_index: usize) {
+        for address in wire_ends(wire) {
+            self.wires_by_address
+                .entry(address)
+                .or_default()
+                .push(wire_index);
+        }
+        self.wires = self.wires.max(wire_index + 1);
+    }
+
+    /// Forgets the component that was at `comp_index`, and moves the entries of `moved`, if any,
+    /// from `from` to `comp_index`.
+    fn swap_remove_component(
+        &mut self,
+        comp: &Component,
+        comp_index: usize,
+        moved: Option<&Component>,
+        from: usize,
+    ) {
+        if self.by_address.get(&comp.address) == Some(&comp_index) {
+            self.by_address.remove(&comp.address);
+        }
+        relink(&mut self.children, comp.parent, comp_index, None);
+        if let Some(moved) = moved {
+            if let Some(index) = self.by_address.get_mut(&moved.address) {
+                if *index == from {
+                    *index = comp_index;
+                }
+            }
+            relink(&mut self.children, moved.parent, from, Some(comp_index));
+        }
+        self.components -= 1;
+    }
+
+    /// Like [`Index::swap_remove_component`] for a wire.
+    fn swap_remove_wire(
+        &mut self,
+        wire: &Wire,
+        wire_index: usize,
+        moved: Option<&Wire>,
+        from: usize,
+    ) {
+        for address in wire_ends(wire) {
+            relink(&mut self.wires_by_address, address, wire_index, None);
+        }
+        if let Some(moved) = moved {
+            for address in wire_ends(moved) {
+                relink(&mut self.wires_by_address, address, from, Some(wire_index));
+            }
+        }
+        self.wires -= 1;
+    }
+}
+
+/// The components a wire is attached to, once each.
+fn wire_ends(wire: &Wire) -> impl Iterator<Item = Address> {
+    let end = (wire.end.component != wire.start.component).then_some(wire.end.component);
+    core::iter::once(wire.start.component).chain(end)
+}
+
+/// Replaces `from` in the list of `key` with `to`, or removes it if `to` is `None`.
+fn relink(lists: &mut HashMap<Address, Vec<usize>>, key: Address, from: usize, to: Option<usize>) {
+    let Some(list) = lists.get_mut(&key) else {
+        return;
+    };
+    let Some(position) = list.iter().position(|&index| index == from) else {
+        return;
+    };
+    match to {
+        Some(to) => list[position] = to,
+        None => {
+            list.swap_remove(position);
+            if list.is_empty() {
+                lists.remove(&key);
+            }
+        }
+    }
+}
+
+impl SaveFile {
+    /// Looks the component up through the address index, falling back to a scan for components
+    /// pushed onto `components` directly instead of through [`SaveFile::push_component`].
+    pub fn find_component(&self, address: Address) -> Option<&Component> {
+        let indexed = self
+            .index
+            .by_address
+            .get(&address)
+            .and_then(|&index| self.components.get(index))
+            .filter(|comp| comp.address == address);
+        indexed.or_else(|| self.components.iter().find(|comp| comp.address == address))
+    }
+
+    pub fn find_component_mut(&mut self, address: Address) -> Option<&mut Component> {
+        let index = self.component_index(address)?;
+        Some(&mut self.components[index])
+    }
+
+    /// Adds a component to the save and indexes it.
+    pub fn push_component(&mut self, comp: Component) {
+        self.index.insert_component(&comp, self.components.len());
+        self.components.push(comp);
+    }
+
+    /// Places `child` on `parent`, or in the world for [`Address::ROOT`], refusing anything that
+    /// would make a parent cycle.
+    pub fn set_parent(&mut self, child: Address, parent: Address) -> Result<()> {
+        let Some(comp_index) = self.component_index(child) else {
+            bail!("No component with address {child}");
+        };
+        let mut ancestor = parent;
+        while ancestor != Address::ROOT {
+            if ancestor == child {
+                bail!("Placing {child} on {parent} would make a parent cycle");
+            }
+            let Some(comp) = self.find_component(ancestor) else {
+                bail!("No component with address {ancestor}");
+            };
+            ancestor = comp.parent;
+        }
+        let old_parent = core::mem::replace(&mut self.components[comp_index].parent, parent);
+        relink(&mut self.index.children, old_parent, comp_index, None);
+        self.index
+            .children
+            .entry(parent)
+            .or_default()
+            .push(comp_index);
+        Ok(())
+    }
+
+    /// Removes a component together with every wire attached to it, and removes or keeps the
+    /// components placed on it depending on `children`.
+    ///
+    /// The last component and wires take the places of the removed ones in `components` and
+    /// `wires`, use [`SaveFile::remove_components`] to keep the order or to remove many
+    /// components in one pass.
+    ///
+    /// State ids nothing left in the save uses are cleared and handed out again. The states
+    /// buffer itself is not shrunk.
+    pub fn remove_component(
+        &mut self,
+        address: Address,
+        children: ChildPolicy,
+    ) -> Result<RemovedComponent> {
+        self.refresh_index();
+        let Some(root) = self.component_index(address) else {
+            bail!("No component with address {address}");
+        };
+
+        let mut removed = vec![root];
+        let mut seen: HashSet<usize> = HashSet::from_iter([root]);
+        let mut queue = vec![address];
+        while let Some(parent) = queue.pop() {
+            for child in self.child_indices(parent) {
+                match children {
+                    ChildPolicy::Remove => {
+                        if seen.insert(child) {
+                            removed.push(child);
+                            queue.push(self.components[child].address);
+                        }
+                    }
+                    ChildPolicy::MoveToRoot => {
+                        self.components[child].parent = Address::ROOT;
+                        relink(&mut self.index.children, parent, child, None);
+                        self.index
+                            .children
+                            .entry(Address::ROOT)
+                            .or_default()
+                            .push(child);
+                    }
+                }
+            }
+        }
+        let addresses: HashSet<Address> = removed
+            .iter()
+            .map(|&index| self.components[index].address)
+            .collect();
+
+        let mut wire_indices: Vec<usize> = addresses
+            .iter()
+            .filter_map(|address| self.index.wires_by_address.get(address))
+            .flatten()
+            .copied()
+            .filter(|&index| {
+                self.wires.get(index).is_some_and(|wire| {
+                    addresses.contains(&wire.start.component)
+                        || addresses.contains(&wire.end.component)
+                })
+            })
+            .collect();
+        wire_indices.sort_unstable();
+        wire_indices.dedup();
+        // Removed from the back, so the entry swapped into a removed slot is never removed itself.
+        let mut wires = Vec::with_capacity(wire_indices.len());
+        for &index in wire_indices.iter().rev() {
+            let wire = self.wires.swap_remove(index);
+            let from = self.wires.len();
+            self.index
+                .swap_remove_wire(&wire, index, self.wires.get(index), from);
+            wires.push(wire);
+        }
+        wires.reverse();
+
+        removed.sort_unstable();
+        let mut comps = Vec::with_capacity(removed.len());
+        for &index in removed.iter().rev() {
+            let comp = self.components.swap_remove(index);
+            let from = self.components.len();
+            self.index
+                .swap_remove_component(&comp, index, self.components.get(index), from);
+            comps.push((index, comp));
+        }
+        comps.reverse();
+        for address in &addresses {
+            self.index.children.remove(address);
+            self.index.wires_by_address.remove(address);
+        }
+        let root_position = comps
+            .iter()
+            .position(|&(index, _)| index == root)
+            .expect("the root is removed");
+        let (_, component) = comps.remove(root_position);
+        let descendants: Vec<Component> = comps.into_iter().map(|(_, comp)| comp).collect();
+
+        let still_used = self.used_state_ids();
+        let freed: Vec<StateId> = core::iter::once(&component)
+            .chain(&descendants)
+            .flat_map(|comp| comp.inputs.iter().chain(&comp.outputs))
+            .chain(wires.iter().map(|wire| &wire.state_id))
+            .copied()
+            .filter(|id| !still_used.contains(id))
+            .collect();
+        self.free_states(freed);
+
+        Ok(RemovedComponent {
+            component,
+            descendants,
+            wires,
+        })
+    }
+
+    /// Like [`SaveFile::remove_component`] for many components at once, in a single pass over
+    /// the save instead of one per component.
+    ///
+    /// A listed component that sits on another listed one is returned on its own rather than as
+    /// a descendant. Nothing is removed if any address is missing.
+    pub fn remove_components(
+        &mut self,
+        addresses: &[Address],
+        children: ChildPolicy,
+    ) -> Result<Vec<RemovedComponent>> {
+        self.refresh_index();
+        let mut group: HashMap<Address, usize> = HashMap::new();
+        let mut roots = Vec::new();
+        for &address in addresses {
+            if self.find_component(address).is_none() {
+                bail!("No component with address {address}");
+            }
+            let before = group.len();
+            group.entry(address).or_insert(roots.len());
+            if group.len() > before {
+                roots.push(address);
+            }
+        }
+        if children == ChildPolicy::Remove {
+            for (index, &root) in roots.iter().enumerate() {
+                let mut queue = vec![root];
+                while let Some(parent) = queue.pop() {
+                    for child in self.children_of(parent) {
+                        let before = group.len();
+                        group.entry(child.address).or_insert(index);
+                        if group.len() > before {
+                            queue.push(child.address);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut removed_roots: Vec<Option<Component>> = vec![None; roots.len()];
+        let mut descendants: Vec<Vec<Component>> = vec![Vec::new(); roots.len()];
+        let mut removed_wires: Vec<Vec<Wire>> = vec![Vec::new(); roots.len()];
+        for mut comp in core::mem::take(&mut self.components) {
+            match group.get(&comp.address) {
+                Some(&index) if comp.address == roots[index] && removed_roots[index].is_none() => {
+                    removed_roots[index] = Some(comp);
+                }
+                Some(&index) => descendants[index].push(comp),
+                None => {
+                    if group.contains_key(&comp.parent) {
+                        comp.parent = Address::ROOT;
+                    }
+                    self.components.push(comp);
+                }
+            }
+        }
+        for wire in core::mem::take(&mut self.wires) {
+            let owner = group
+                .get(&wire.start.component)
+                .or_else(|| group.get(&wire.end.component));
+            match owner {
+                Some(&index) => removed_wires[index].push(wire),
+                None => self.wires.push(wire),
+            }
+        }
+        self.rebuild_index();
+
+        let still_used = self.used_state_ids();
+        let freed: Vec<StateId> = removed_roots
+            .iter()
+            .flatten()
+            .chain(descendants.iter().flatten())
+            .flat_map(|comp| comp.inputs.iter().chain(&comp.outputs))
+            .chain(removed_wires.iter().flatten().map(|wire| &wire.state_id))
+            .copied()
+            .filter(|id| !still_used.contains(id))
+            .collect();
+        self.free_states(freed);
+
+        Ok(removed_roots
+            .into_iter()
+            .zip(descendants)
+            .zip(removed_wires)
+            .map(|((component, descendants), wires)| RemovedComponent {
+                component: component.expect("every root was found before removing"),
+                descendants,
+                wires,
+            })
+            .collect())
+    }
+
+    /// Components placed directly on the component at `parent`.
+    pub fn children_of(&self, parent: Address) -> Vec<&Component> {
+        let Some(indices) = self.index.children.get(&parent) else {
+            return Vec::new();
+        };
+        indices
+            .iter()
+            .filter_map(|&index| self.components.get(index))
+            .filter(|comp| comp.parent == parent)
+            .collect()
+    }
+
+    /// Components that are not placed on another component.
+    pub fn top_level_components(&self) -> Vec<&Component> {
+        self.children_of(Address::ROOT)
+    }
+
+    /// Adds a wire to the save and indexes it.
+    pub fn push_wire(&mut self, wire: Wire) {
+        self.index.insert_wire(&wire, self.wires.len());
+        self.wires.push(wire);
+    }
+
+    /// Every wire with either end on the component at `address`.
+    pub fn wires_for_component(&self, address: Address) -> Vec<&Wire> {
+        let Some(indices) = self.index.wires_by_address.get(&address) else {
+            return Vec::new();
+        };
+        indices
+            .iter()
+            .filter_map(|&index| self.wires.get(index))
+            .filter(|wire| wire.start.component == address || wire.end.component == address)
+            .collect()
+    }
+
+    pub fn wires_for_peg(&self, peg: &PegAddress) -> Vec<&Wire> {
+        self.wires_for_component(peg.component)
+            .into_iter()
+            .filter(|wire| wire.start == *peg || wire.end == *peg)
+            .collect()
+    }
+
+    /// Rebuilds the lookup tables after `components` or `wires` was modified directly.
+    pub fn rebuild_index(&mut self) {
+        self.index = Index::build(&self.components, &self.wires);
+    }
+
+    /// Rebuilds the lookup tables if components or wires were added or removed directly.
+    fn refresh_index(&mut self) {
+        if self.index.components != self.components.len() || self.index.wires != self.wires.len() {
+            self.rebuild_index();
+        }
+    }
+
+    /// Positions in `components` of the components placed directly on `parent`.
+    fn child_indices(&self, parent: Address) -> Vec<usize> {
+        let Some(indices) = self.index.children.get(&parent) else {
+            return Vec::new();
+        };
+        indices
+            .iter()
+            .copied()
+            .filter(|&index| {
+                self.components
+                    .get(index)
+                    .is_some_and(|comp| comp.parent == parent && comp.address != parent)
+            })
+            .collect()
+    }
+
+    /// Every state id a component or wire of the save uses.
+    fn used_state_ids(&self) -> HashSet<StateId> {
+        self.components
+            .iter()
+            .flat_map(|comp| comp.inputs.iter().chain(&comp.outputs))
+            .chain(self.wires.iter().map(|wire| &wire.state_id))
+            .copied()
+            .collect()
+    }
+
+    /// Clears state ids nothing uses anymore and queues them to be handed out again.
+    fn free_states(&mut self, freed: Vec<StateId>) {
+        let freed = freed
+            .into_iter()
+            .filter(|id| id.0 > 0 && *id <= self.highest_state_id);
+        for state_id in freed {
+            if self.states.get_bit(state_id) {
+                self.states.set(state_id, false);
+            }
+            self.free_state_ids.push(state_id);
+        }
+        // Popped from the back, so the lowest ids are reused first.
+        self.free_state_ids.sort_unstable_by(|a, b| b.cmp(a));
+        self.free_state_ids.dedup();
+    }
+
+    /// Position of the component in `components`, repairing the index if it was stale.
+    pub(crate) fn component_index(&mut self, address: Address) -> Option<usize> {
+        if let Some(&index) = self.index.by_address.get(&address) {
+            if self
+                .components
+                .get(index)
+                .is_some_and(|comp| comp.address == address)
+            {
+                return Some(index);
+            }
+        }
+        let index = self
+            .components
+            .iter()
+            .position(|comp| comp.address == address)?;
+        self.index.by_address.insert(address, index);
+        Some(index)
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+
+/// Input the [`Parser`](crate::Parser) reads from.
+///
+/// With the `std` feature every [`std::io::Read`] is a source, without it byte slices are.
+pub trait Source {
+    /// Reads up to `buf.len()` bytes and returns how many were read, `0` means the input ended.
+    fn read_some(&mut self, buf: &mut [u8]) -> Result<usize, ParseError>;
+
+    /// Up to `len` of the bytes the next reads will return, without consuming them. Only used to
+    /// show context in errors, so sources that can't look ahead return nothing.
+    fn upcoming(&self, len: usize) -> &[u8] {
+        let _ = len;
+        &[]
+    }
+}
+
+/// A byte slice source that can show upcoming bytes in errors, used by
+/// [`Parser::from_bytes`](crate::Parser::from_bytes).
+pub struct SliceSource<'a>(pub &'a [u8]);
+
+impl Source for SliceSource<'_> {
+    fn read_some(&mut self, buf: &mut [u8]) -> Result<usize, ParseError> {
+        let amount = buf.len().min(self.0.len());
+        let (head, tail) = self.0.split_at(amount);
+        buf[..amount].copy_from_slice(head);
+        self.0 = tail;
+        Ok(amount)
+    }
+
+    fn upcoming(&self, len: usize) -> &[u8] {
+        &self.0[..len.min(self.0.len())]
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Source for R {
+    fn read_some(&mut self, buf: &mut [u8]) -> Result<usize, ParseError> {
+        loop {
+            match self.read(buf) {
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                result => return Ok(result?),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Source for &[u8] {
+    fn read_some(&mut self, buf: &mut [u8]) -> Result<usize, ParseError> {
+        let amount = buf.len().min(self.len());
+        let (head, tail) = self.split_at(amount);
+        buf[..amount].copy_from_slice(head);
+        *self = tail;
+        Ok(amount)
+    }
+}
+
+/// Output the [`Writer`](crate::Writer) writes to.
+///
+/// With the `std` feature every [`std::io::Write`] is a sink, without it only `Vec<u8>` is.
+pub trait Sink {
+    fn write_bytes(&mut self, bytes: &[u8]) -> anyhow::Result<()>;
+
+    fn flush_bytes(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Sink for W {
+    fn write_bytes(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        Ok(self.write_all(bytes)?)
+    }
+
+    fn flush_bytes(&mut self) -> anyhow::Result<()> {
+        Ok(self.flush()?)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Sink for Vec<u8> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! Parsing and writing of Logic World `data.logicworld` save files.
+//!
+//! Without the default `std` feature the crate only needs `core` and `alloc`, parsing from byte
+//! slices and writing into `Vec<u8>`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+mod async_io;
+mod builder;
+mod compact;
+mod diff;
+mod dot;
+mod error;
+mod extract;
+mod graph;
+mod index;
+mod io;
+mod merge;
+mod parse;
+mod query;
+mod save;
+#[cfg(feature = "serde")]
+mod serialize;
+mod states;
+mod stats;
+mod transform;
+mod types;
+mod validate;
+mod write;
+
+#[cfg(feature = "tokio")]
+pub use async_io::{parse_save_async, write_save_async};
+pub use builder::{CircuitBuilder, ComponentBuilder};
+pub use diff::{ComponentDiff, SaveDiff, WireKey};
+pub use error::{Hexdump, ParseError, Section, WriteError, WriteIssue};
+pub use graph::{PegNode, WireGraph};
+pub use index::{ChildPolicy, RemovedComponent};
+pub use io::{Sink, SliceSource, Source};
+pub use merge::MergeConflict;
+pub use parse::{
+    parse_save_bytes,
+    ParseLimits,
+    ParseOptions,
+    Parser,
+    PartialSaveFile,
+    Recovery,
+    SaveHeader,
+};
+#[cfg(feature = "std")]
+pub use save::BackupPolicy;
+pub use save::{CompMap, SaveFile, FORMAT_VERSION};
+pub use stats::SaveStats;
+pub use types::{
+    Address,
+    Axis,
+    BoundingBox,
+    Color,
+    Component,
+    CustomData,
+    PegAddress,
+    PegType,
+    Quat,
+    SaveType,
+    StateId,
+    States,
+    Vec3,
+    Vec3f,
+    Version,
+    Wire,
+};
+pub use validate::{Severity, ValidationError};
+pub use write::Writer;
+
+mod collections {
+    #[cfg(feature = "std")]
+    pub(crate) use std::collections::{HashMap, HashSet};
+
+    #[cfg(not(feature = "std"))]
+    pub(crate) use hashbrown::{HashMap, HashSet};
+
+    #[cfg(feature = "std")]
+    pub(crate) type IndexMap<K, V> = indexmap::IndexMap<K, V>;
+
+    #[cfg(not(feature = "std"))]
+    pub(crate) type IndexMap<K, V> = indexmap::IndexMap<K, V, hashbrown::DefaultHashBuilder>;
+}
+
+// Saves are regularly processed on worker threads, keep them shareable.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SaveFile>();
+};
@@ -0,0 +1,130 @@
+use alloc::format;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::collections::HashMap;
+use crate::save::SaveFile;
+use crate::types::{Address, PegAddress, StateId, Vec3};
+
+/// What [`SaveFile::merge_with`] does when the saves use different versions of a mod.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeConflict {
+    #[default]
+    Fail,
+    /// Keep the version of the save merged into.
+    PreferBase,
+    /// Take the version of the incoming save.
+    PreferOther,
+}
+
+impl SaveFile {
+    /// Appends every component and wire of `other` to this save, giving them fresh addresses and
+    /// state ids so nothing collides. Incoming components are moved by `offset` if given.
+    ///
+    /// Incoming state ids are moved past `highest_state_id`, except for those of 0 and below.
+    ///
+    /// Fails if the saves use different versions of a mod, see [`SaveFile::merge_with`].
+    pub fn merge(self, other: SaveFile, offset: Option<Vec3>) -> Result<SaveFile> {
+        self.merge_with(other, offset, MergeConflict::Fail)
+    }
+
+    pub fn merge_with(
+        mut self,
+        other: SaveFile,
+        offset: Option<Vec3>,
+        conflicts: MergeConflict,
+    ) -> Result<SaveFile> {
+        for (name, version) in &other.mod_versions {
+            match self.mod_versions.get(name) {
+                Some(existing) if existing != version => match conflicts {
+                    MergeConflict::Fail => {
+                        bail!("Mod {name} is {existing:?} in one save and {version:?} in the other")
+                    }
+                    MergeConflict::PreferBase => {}
+                    MergeConflict::PreferOther => {
+                        self.mod_versions.insert(name.clone(), version.clone());
+                    }
+                },
+                Some(_) => {}
+                None => {
+                    self.mod_versions.insert(name.clone(), version.clone());
+                }
+            }
+        }
+
+        for (name, raw) in other.raw_strings {
+            match self.raw_strings.get(&name) {
+                Some(existing) if *existing != raw => {
+                    bail!("Both saves have a string named {name:?} but with different bytes")
+                }
+                Some(_) => {}
+                None => {
+                    self.raw_strings.insert(name, raw);
+                }
+            }
+        }
+        for (_, name) in other.comp_map.in_order() {
+            self.comp_map.ensure(name);
+        }
+
+        let mut addresses = HashMap::with_capacity(other.components.len());
+        for comp in &other.components {
+            addresses.insert(comp.address, self.get_free_address());
+        }
+        let rebase_address = |address: Address| {
+            addresses
+                .get(&address)
+                .copied()
+                .ok_or_else(|| anyhow!("Missing component {address} in merged save"))
+        };
+
+        // Ids of 0 and below don't point into the states buffer, like in `compact_state_ids`.
+        let state_base = self.highest_state_id.0;
+        let rebase_state = |StateId(id): StateId| -> Result<StateId> {
+            if id <= 0 {
+                return Ok(StateId(id));
+            }
+            id.checked_add(state_base)
+                .map(StateId)
+                .ok_or_else(|| anyhow!("Rebasing state id {id} overflows"))
+        };
+        for id in (1..=other.highest_state_id.0).map(StateId) {
+            self.states.set(rebase_state(id)?, other.states.get_bit(id));
+        }
+        self.highest_state_id = self
+            .highest_state_id
+            .max(rebase_state(other.highest_state_id)?);
+
+        for mut comp in other.components {
+            comp.address = rebase_address(comp.address)?;
+            if comp.parent != Address::ROOT {
+                comp.parent = rebase_address(comp.parent)
+                    .with_context(|| format!("Rebasing parent of {}", comp.address))?;
+            }
+            for state_id in comp.inputs.iter_mut().chain(comp.outputs.iter_mut()) {
+                *state_id = rebase_state(*state_id)?;
+            }
+            if let Some(offset) = &offset {
+                comp.position = comp.position.checked_add(offset).ok_or_else(|| {
+                    anyhow!("Offset moves component {} out of range", comp.address)
+                })?;
+            }
+            self.push_component(comp);
+        }
+
+        for mut wire in other.wires {
+            let rebase_peg = |peg: PegAddress| -> Result<PegAddress> {
+                Ok(PegAddress {
+                    component: rebase_address(peg.component)?,
+                    ..peg
+                })
+            };
+            wire.start = rebase_peg(wire.start)?;
+            wire.end = rebase_peg(wire.end)?;
+            wire.state_id = rebase_state(wire.state_id)?;
+            self.push_wire(wire);
+        }
+
+        Ok(self)
+    }
+}
@@ -0,0 +1,865 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::collections::{HashMap, HashSet};
+use crate::error::{Hexdump, ParseError, Section};
+use crate::index::Index;
+use crate::io::{SliceSource, Source};
+use crate::save::{CompMap, SaveFile, FORMAT_VERSION};
+use crate::types::{
+    Address,
+    Color,
+    Component,
+    CustomData,
+    PegAddress,
+    PegType,
+    Quat,
+    SaveType,
+    StateId,
+    States,
+    Vec3,
+    Version,
+    Wire,
+};
+
+type Result<T> = core::result::Result<T, ParseError>;
+
+/// Whatever could be read from a damaged save, see [`Parser::parse_save_lenient`].
+///
+/// Sections that were never reached are `None`, `components` and `wires` hold every entry that
+/// was read completely.
+#[derive(Debug, Clone, Default)]
+pub struct PartialSaveFile {
+    pub format_version: Option<u8>,
+    pub game_version: Option<Version>,
+    pub save_type: Option<SaveType>,
+    pub mod_versions: Option<HashMap<Box<str>, Version>>,
+    pub comp_map: Option<CompMap>,
+    pub expected_components: Option<usize>,
+    pub components: Vec<Component>,
+    pub expected_wires: Option<usize>,
+    pub wires: Vec<Wire>,
+    pub states: Option<States>,
+    pub footer: bool,
+}
+
+/// What a save declares before its component map, see [`Parser::parse_header_only`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveHeader {
+    pub game_version: Version,
+    pub mod_versions: HashMap<Box<str>, Version>,
+    pub component_count: usize,
+    pub wire_count: usize,
+}
+
+impl PartialSaveFile {
+    pub fn components_complete(&self) -> bool {
+        self.expected_components == Some(self.components.len())
+    }
+
+    pub fn wires_complete(&self) -> bool {
+        self.expected_wires == Some(self.wires.len())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Read to the end of the input after the footer and fail if anything follows it.
+    pub reject_trailing_bytes: bool,
+    /// Try the known layout on saves with a newer format version instead of failing, the
+    /// version is kept in [`SaveFile::format_version`] and reported in [`Parser::warnings`].
+    pub allow_unknown_version: bool,
+    /// Fail on wires connected to components that aren't in the save, see
+    /// [`SaveFile::validate_wires`] to collect them instead.
+    pub reject_dangling_wires: bool,
+    /// Give components that reuse an earlier component's address a new one instead of failing,
+    /// wires and children keep referring to the first component.
+    pub renumber_duplicate_addresses: bool,
+    pub limits: ParseLimits,
+}
+
+/// Bounds on the counts a save may claim, checked before anything is allocated for them.
+///
+/// When the input length is known counts are also checked against the bytes left, so a corrupted
+/// count fails cleanly even with the default limits.
+#[derive(Debug, Clone)]
+pub struct ParseLimits {
+    pub max_components: usize,
+    pub max_wires: usize,
+    pub max_string_len: usize,
+    pub max_custom_data_len: usize,
+    pub max_states_len: usize,
+    /// Vectors reserve at most this many entries up front and grow while reading past it.
+    pub max_preallocate: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_components: usize::MAX,
+            max_wires: usize::MAX,
+            max_string_len: usize::MAX,
+            max_custom_data_len: usize::MAX,
+            max_states_len: usize::MAX,
+            max_preallocate: 4096,
+        }
+    }
+}
+
+/// A fix applied by [`Parser::parse_with_recovery`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// The states count didn't match where the footer is, `claimed` is `None` if it was negative.
+    StatesLength {
+        claimed: Option<usize>,
+        found: usize,
+    },
+    /// Bytes after the footer that were ignored.
+    TrailingBytes(usize),
+}
+
+const HEADER: &[u8; 16] = b"Logic World save";
+const FOOTER: &[u8; 16] = b"redstone sux lol";
+
+/// How many bytes before and after a failure are shown in errors.
+const HEXDUMP_CONTEXT: usize = 32;
+
+// Smallest encoded size of each repeated item, used to reject counts the input can't hold.
+const MIN_COMPONENT_SIZE: u64 = 50;
+const WIRE_SIZE: u64 = 26;
+const COMP_MAP_ENTRY_MIN_SIZE: u64 = 6;
+const MOD_VERSION_MIN_SIZE: u64 = 20;
+
+pub struct Parser<R: Source> {
+    options: ParseOptions,
+    reader: CountingReader<R>,
+    section: Section,
+    id_mapping: CompMap,
+    highest_state_id: StateId,
+    input_len: Option<u64>,
+    raw_strings: HashMap<Box<str>, Box<[u8]>>,
+    mod_order: Vec<Box<str>>,
+    /// Problems that were tolerated because of the options.
+    warnings: Vec<ParseError>,
+    recover_footer: bool,
+    recoveries: Vec<Recovery>,
+}
+
+/// Keeps track of how many bytes were consumed, including those of a read that failed halfway.
+struct CountingReader<R> {
+    inner: R,
+    offset: u64,
+    /// The last bytes read, shown around errors.
+    recent: VecDeque<u8>,
+    /// Where the value read last starts, which is what the caret points at.
+    last_read: u64,
+}
+
+impl<R: Source> CountingReader<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        if self.read_up_to(buf)? < buf.len() {
+            return Err(ParseError::UnexpectedEof);
+        }
+        Ok(())
+    }
+
+    /// Fills as much of `buf` as the input has left, returning how much that was.
+    fn read_up_to(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.last_read = self.offset;
+        let mut filled = 0;
+        while filled < buf.len() {
+            let amount = self.inner.read_some(&mut buf[filled..])?;
+            if amount == 0 {
+                break;
+            }
+            self.remember(&buf[filled..filled + amount]);
+            filled += amount;
+            self.offset += amount as u64;
+        }
+        Ok(filled)
+    }
+
+    fn remember(&mut self, bytes: &[u8]) {
+        let keep = &bytes[bytes.len().saturating_sub(HEXDUMP_CONTEXT)..];
+        let overflow = (self.recent.len() + keep.len()).saturating_sub(HEXDUMP_CONTEXT);
+        self.recent.drain(..overflow);
+        self.recent.extend(keep);
+    }
+
+    /// The bytes around the current position, without reading any further.
+    fn hexdump(&self) -> Hexdump {
+        let mut bytes: Vec<u8> = self.recent.iter().copied().collect();
+        bytes.extend_from_slice(self.inner.upcoming(HEXDUMP_CONTEXT));
+        Hexdump {
+            start: self.offset - self.recent.len() as u64,
+            bytes,
+            caret: self.last_read,
+        }
+    }
+
+    fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        let mut rest = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let amount = self.inner.read_some(&mut buf)?;
+            if amount == 0 {
+                return Ok(rest);
+            }
+            rest.extend_from_slice(&buf[..amount]);
+            self.offset += amount as u64;
+        }
+    }
+
+    /// Consumes the rest of the input, returning how many bytes were left.
+    fn skip_to_end(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 4096];
+        let start = self.offset;
+        loop {
+            let amount = self.inner.read_some(&mut buf)?;
+            if amount == 0 {
+                return Ok(self.offset - start);
+            }
+            self.offset += amount as u64;
+        }
+    }
+}
+
+/// Parses a save that is already fully loaded in memory.
+pub fn parse_save_bytes(bytes: &[u8]) -> Result<SaveFile> {
+    Parser::from_bytes(bytes).parse_save()
+}
+
+impl<'a> Parser<SliceSource<'a>> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        Self::new(SliceSource(bytes)).with_input_len(bytes.len() as u64)
+    }
+}
+
+impl<R: Source> Parser<R> {
+    /// Creates a parser reading from any source, the format is strictly sequential so no `Seek`
+    /// is required.
+    pub fn new(reader: R) -> Self {
+        Self {
+            options: ParseOptions::default(),
+            reader: CountingReader {
+                inner: reader,
+                offset: 0,
+                recent: VecDeque::with_capacity(HEXDUMP_CONTEXT),
+                last_read: 0,
+            },
+            section: Section::Header,
+            id_mapping: CompMap::with_capacity(0),
+            highest_state_id: StateId(0),
+            input_len: None,
+            raw_strings: HashMap::new(),
+            mod_order: Vec::new(),
+            warnings: Vec::new(),
+            recover_footer: false,
+            recoveries: Vec::new(),
+        }
+    }
+
+    /// Tells the parser how long the input is, letting it reject counts that can't fit in it.
+    pub fn with_input_len(mut self, len: u64) -> Self {
+        self.input_len = Some(len);
+        self
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parses the whole save, errors report the section and byte offset where parsing stopped.
+    ///
+    /// Problems the options let through are kept in [`Parser::warnings`].
+    pub fn parse_save(&mut self) -> Result<SaveFile> {
+        self.parse()
+    }
+
+    /// Parses the whole save, and if the footer isn't where the states count says it is, takes
+    /// the states to end wherever the footer actually is.
+    ///
+    /// Returns what had to be fixed, an empty list means the save was intact.
+    pub fn parse_with_recovery(&mut self) -> Result<(SaveFile, Vec<Recovery>)> {
+        self.recover_footer = true;
+        let save = self.parse()?;
+        Ok((save, core::mem::take(&mut self.recoveries)))
+    }
+
+    /// Problems that were tolerated because of the [`ParseOptions`], such as an unknown format
+    /// version or a renumbered duplicate address, in the order they were found.
+    pub fn warnings(&self) -> &[ParseError] {
+        &self.warnings
+    }
+
+    fn parse(&mut self) -> Result<SaveFile> {
+        let mut partial = PartialSaveFile::default();
+        if let Err(source) = self.read_into(&mut partial) {
+            return Err(self.locate(source));
+        }
+
+        let PartialSaveFile {
+            format_version: Some(format_version),
+            game_version: Some(game_version),
+            save_type: Some(save_type),
+            mod_versions: Some(mod_versions),
+            comp_map: Some(comp_map),
+            components,
+            wires,
+            states: Some(states),
+            ..
+        } = partial
+        else {
+            unreachable!("a successful read fills every section");
+        };
+
+        let highest_address = components
+            .iter()
+            .map(|comp| comp.address)
+            .max()
+            .unwrap_or(Address(1));
+
+        Ok(SaveFile {
+            format_version,
+            game_version,
+            save_type,
+            mod_versions,
+            comp_map,
+            index: Index::build(&components, &wires),
+            components,
+            wires,
+            states,
+            highest_state_id: self.highest_state_id,
+            highest_address,
+            raw_strings: core::mem::take(&mut self.raw_strings),
+            mod_order: core::mem::take(&mut self.mod_order),
+            free_state_ids: Vec::new(),
+        })
+    }
+
+    /// Parses as much of the save as possible, keeping everything read before the first error.
+    ///
+    /// Useful to salvage saves the game left truncated after crashing mid-write.
+    pub fn parse_save_lenient(mut self) -> (PartialSaveFile, Vec<ParseError>) {
+        let mut partial = PartialSaveFile::default();
+        let result = self.read_into(&mut partial);
+        let mut errors = core::mem::take(&mut self.warnings);
+        if let Err(source) = result {
+            errors.push(self.locate(source));
+        }
+        (partial, errors)
+    }
+
+    /// Reads only the header and mod versions, for listing saves without loading them.
+    pub fn parse_header_only(mut self) -> Result<SaveHeader> {
+        let mut partial = PartialSaveFile::default();
+        let (component_count, wire_count) = match self.read_header_sections(&mut partial) {
+            Ok(counts) => counts,
+            Err(source) => return Err(self.locate(source)),
+        };
+        let PartialSaveFile {
+            game_version: Some(game_version),
+            mod_versions: Some(mod_versions),
+            ..
+        } = partial
+        else {
+            unreachable!("a successful read fills the header sections");
+        };
+        Ok(SaveHeader {
+            game_version,
+            mod_versions,
+            component_count,
+            wire_count,
+        })
+    }
+
+    fn locate(&self, source: ParseError) -> ParseError {
+        ParseError::At {
+            section: self.section,
+            offset: self.reader.offset,
+            context: self.reader.hexdump(),
+            source: Box::new(source),
+        }
+    }
+
+    fn read_into(&mut self, partial: &mut PartialSaveFile) -> Result<()> {
+        let (num_components, num_wires) = self.read_header_sections(partial)?;
+
+        self.section = Section::CompMap;
+        self.read_comp_map()?;
+        partial.comp_map = Some(self.id_mapping.clone());
+
+        partial.components.reserve(self.preallocate(num_components));
+        let mut seen: HashMap<Address, (usize, u64)> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for index in 0..num_components {
+            self.section = Section::Component(index);
+            let offset = self.reader.offset;
+            let comp = self.read_component()?;
+            if let Some(&(first, first_offset)) = seen.get(&comp.address) {
+                let error = ParseError::DuplicateAddress {
+                    address: comp.address,
+                    first,
+                    first_offset,
+                };
+                if !self.options.renumber_duplicate_addresses {
+                    return Err(error);
+                }
+                let warning = self.locate(error);
+                self.warnings.push(warning);
+                duplicates.push(index);
+            } else {
+                seen.insert(comp.address, (index, offset));
+            }
+            partial.components.push(comp);
+        }
+        // Wires and children can't tell the copies apart, so they stay with the first one.
+        let mut next = seen.keys().max().copied().unwrap_or(Address::ROOT);
+        for index in duplicates {
+            next.0 += 1;
+            partial.components[index].address = next;
+        }
+
+        let addresses: Option<HashSet<Address>> = self
+            .options
+            .reject_dangling_wires
+            .then(|| partial.components.iter().map(|comp| comp.address).collect());
+        partial.wires.reserve(self.preallocate(num_wires));
+        for index in 0..num_wires {
+            self.section = Section::Wire(index);
+            let wire = self.read_wire()?;
+            if let Some(addresses) = &addresses {
+                for peg in [&wire.start, &wire.end] {
+                    if !addresses.contains(&peg.component) {
+                        return Err(ParseError::DanglingWire(peg.component));
+                    }
+                }
+            }
+            partial.wires.push(wire);
+        }
+
+        self.section = Section::States;
+        if self.recover_footer {
+            partial.states = Some(self.read_states_recovering()?);
+            partial.footer = true;
+            return Ok(());
+        }
+        let num_states = self.read_count("state count", 1, self.options.limits.max_states_len)?;
+        let mut states = Vec::with_capacity(self.preallocate(num_states));
+        for _ in 0..num_states {
+            states.push(self.read_byte()?);
+        }
+        partial.states = Some(States(states));
+
+        self.section = Section::Footer;
+        self.validate_footer()?;
+        partial.footer = true;
+
+        if self.options.reject_trailing_bytes {
+            let trailing = self.reader.skip_to_end()?;
+            if trailing != 0 {
+                return Err(ParseError::TrailingBytes(trailing));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads everything before the component map, returning the component and wire counts.
+    fn read_header_sections(&mut self, partial: &mut PartialSaveFile) -> Result<(usize, usize)> {
+        self.section = Section::Header;
+        self.validate_header()?;
+        partial.format_version = Some(self.read_format_version()?);
+        partial.game_version = Some(self.read_version()?);
+        partial.save_type = Some(self.read_save_type()?);
+
+        let num_components = self.read_count(
+            "component count",
+            MIN_COMPONENT_SIZE,
+            self.options.limits.max_components,
+        )?;
+        let num_wires = self.read_count("wire count", WIRE_SIZE, self.options.limits.max_wires)?;
+        partial.expected_components = Some(num_components);
+        partial.expected_wires = Some(num_wires);
+
+        self.section = Section::ModVersions;
+        partial.mod_versions = Some(self.read_mod_versions()?);
+        Ok((num_components, num_wires))
+    }
+
+    fn read_wire(&mut self) -> Result<Wire> {
+        let start = self.read_peg_address()?;
+        let end = self.read_peg_address()?;
+        let state_id = self.read_state_id()?;
+        let rotation = self.read_float()?;
+
+        Ok(Wire {
+            start,
+            end,
+            state_id,
+            rotation,
+        })
+    }
+
+    fn read_peg_address(&mut self) -> Result<PegAddress> {
+        let type_ = self.read_byte()?;
+        let type_ = match type_ {
+            1 => PegType::Input,
+            2 => PegType::Output,
+            _ => return Err(ParseError::UnknownPegType(type_)),
+        };
+
+        let component = self.read_address()?;
+        let index = self.read_int()?;
+
+        Ok(PegAddress {
+            type_,
+            component,
+            index,
+        })
+    }
+
+    fn read_component(&mut self) -> Result<Component> {
+        let address = self.read_address()?;
+        let parent = self.read_address()?;
+
+        let id = self.read_id()?;
+        let id = self
+            .id_mapping
+            .get_id(id)
+            .map_err(|_| ParseError::MissingCompMapEntry(id))?
+            .clone();
+
+        let position = self.read_pos()?;
+        let rotation = self.read_rot()?;
+
+        let input_count = self.read_count("input count", 4, usize::MAX)?;
+        let mut inputs = Vec::with_capacity(self.preallocate(input_count));
+        for _ in 0..input_count {
+            inputs.push(self.read_state_id()?);
+        }
+        let output_count = self.read_count("output count", 4, usize::MAX)?;
+        let mut outputs = Vec::with_capacity(self.preallocate(output_count));
+        for _ in 0..output_count {
+            outputs.push(self.read_state_id()?);
+        }
+
+        let custom_data = match self.read_int()? {
+            -1 => CustomData::None,
+            count => {
+                let field = "custom data length";
+                let count = usize::try_from(count)
+                    .map_err(|_| ParseError::NegativeCount { field, count })?;
+                self.check_count(field, count, 1, self.options.limits.max_custom_data_len)?;
+                let data = self.read_bytes(count)?;
+                self.parse_custom_data(&id, data)?
+            }
+        };
+
+        Ok(Component {
+            address,
+            parent,
+            id,
+            position,
+            rotation,
+            inputs,
+            outputs,
+            custom_data,
+        })
+    }
+
+    /// Data of an unexpected length is kept as [`CustomData::Unknown`], so it is written back
+    /// byte for byte.
+    fn parse_custom_data(&self, id: &str, data: Vec<u8>) -> Result<CustomData> {
+        Ok(match (id, data.as_slice()) {
+            ("MHG.Switch" | "MHG.Button", &[r, g, b, on @ (0 | 1)]) => CustomData::Switch {
+                color: Color::from_rgb(r, g, b),
+                on: on == 1,
+            },
+            ("MHG.StandingDisplay", &[a, b, c, d]) => CustomData::Display {
+                color_mode: u32::from_le_bytes([a, b, c, d]),
+            },
+            ("MHG.Delayer", &[a, b, c, d]) => CustomData::Delayer {
+                ticks: u32::from_le_bytes([a, b, c, d]),
+            },
+            ("MHG.Label", _) => parse_label(&data).unwrap_or(CustomData::Unknown(data)),
+            _ => CustomData::Unknown(data),
+        })
+    }
+
+    fn read_pos(&mut self) -> Result<Vec3> {
+        Ok(Vec3 {
+            x: self.read_int()?,
+            y: self.read_int()?,
+            z: self.read_int()?,
+        })
+    }
+    fn read_rot(&mut self) -> Result<Quat> {
+        Ok(Quat {
+            x: self.read_float()?,
+            y: self.read_float()?,
+            z: self.read_float()?,
+            w: self.read_float()?,
+        })
+    }
+
+    fn read_comp_map(&mut self) -> Result<()> {
+        let count = self.read_count("component map size", COMP_MAP_ENTRY_MIN_SIZE, usize::MAX)?;
+        self.id_mapping = CompMap::with_capacity(self.preallocate(count));
+
+        for _ in 0..count {
+            let id = self.read_id()?;
+            let name = self.read_string()?;
+            self.id_mapping.insert(id, name.into());
+        }
+
+        Ok(())
+    }
+
+    fn read_mod_versions(&mut self) -> Result<HashMap<Box<str>, Version>> {
+        let count = self.read_count("mod version count", MOD_VERSION_MIN_SIZE, usize::MAX)?;
+        let mut mapping = HashMap::with_capacity(self.preallocate(count));
+        for _ in 0..count {
+            let name = self.read_string()?;
+            let version = self.read_version()?;
+            if mapping.insert(name.clone(), version).is_none() {
+                self.mod_order.push(name);
+            }
+        }
+
+        Ok(mapping)
+    }
+
+    fn validate_header(&mut self) -> Result<()> {
+        let mut header = [0u8; 16];
+        let read = self.reader.read_up_to(&mut header)?;
+        if read == 0 {
+            Err(ParseError::EmptyFile)
+        } else if &header[..read] != HEADER {
+            Err(ParseError::InvalidHeader {
+                found: header[..read].to_vec(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads the rest of the input and looks for the footer in it, preferring the position the
+    /// states count claims.
+    fn read_states_recovering(&mut self) -> Result<States> {
+        let claimed = self.read_int()?;
+        let rest = self.reader.read_to_end()?;
+
+        let footer_at = |at: usize| rest.get(at..at + FOOTER.len()) == Some(FOOTER);
+        let claimed = usize::try_from(claimed).ok();
+        let found = claimed
+            .filter(|&at| footer_at(at))
+            .or_else(|| (0..rest.len()).find(|&at| footer_at(at)));
+        let Some(found) = found else {
+            self.section = Section::Footer;
+            let tail = &rest[rest.len().saturating_sub(FOOTER.len())..];
+            return Err(ParseError::UnexpectedFooter {
+                found: tail.to_vec(),
+            });
+        };
+
+        if claimed != Some(found) {
+            self.recoveries
+                .push(Recovery::StatesLength { claimed, found });
+        }
+        let trailing = rest.len() - found - FOOTER.len();
+        if trailing != 0 {
+            self.recoveries.push(Recovery::TrailingBytes(trailing));
+        }
+
+        let mut states = rest;
+        states.truncate(found);
+        Ok(States(states))
+    }
+
+    fn validate_footer(&mut self) -> Result<()> {
+        let mut footer = [0u8; 16];
+        let read = self.reader.read_up_to(&mut footer)?;
+        if read < FOOTER.len() {
+            Err(ParseError::MissingFooter {
+                missing: FOOTER.len() - read,
+            })
+        } else if &footer != FOOTER {
+            Err(ParseError::UnexpectedFooter {
+                found: footer.to_vec(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_format_version(&mut self) -> Result<u8> {
+        let version = self.read_byte()?;
+        // The layouts before 7 aren't documented anywhere, guessing at them would silently
+        // produce garbage.
+        if version < FORMAT_VERSION {
+            return Err(ParseError::LegacyFormatVersion(version));
+        }
+        if version != FORMAT_VERSION {
+            if !self.options.allow_unknown_version {
+                return Err(ParseError::UnsupportedFormatVersion(version));
+            }
+            let warning = self.locate(ParseError::UnsupportedFormatVersion(version));
+            self.warnings.push(warning);
+        }
+        Ok(version)
+    }
+
+    fn read_version(&mut self) -> Result<Version> {
+        Ok(Version(
+            self.read_int()?,
+            self.read_int()?,
+            self.read_int()?,
+            self.read_int()?,
+        ))
+    }
+
+    fn read_save_type(&mut self) -> Result<SaveType> {
+        let save_type = self.read_byte()?;
+        SaveType::from_byte(save_type).ok_or(ParseError::InvalidSaveType(save_type))
+    }
+
+    fn read_string(&mut self) -> Result<Box<str>> {
+        let count = self.read_count("string length", 1, self.options.limits.max_string_len)?;
+        let data = self.read_bytes(count)?;
+        // Mods may write any bytes, keep them around to write back instead of failing the parse.
+        let data = match String::from_utf8(data) {
+            Ok(data) => data.into_boxed_str(),
+            Err(err) => {
+                let raw = err.into_bytes().into_boxed_slice();
+                let mut name = escape_invalid_utf8(&raw);
+                // Only possible if a name already contains the escape characters.
+                while self
+                    .raw_strings
+                    .get(name.as_str())
+                    .is_some_and(|other| *other != raw)
+                {
+                    name.push(char::REPLACEMENT_CHARACTER);
+                }
+                let name: Box<str> = name.into();
+                self.raw_strings.insert(name.clone(), raw);
+                name
+            }
+        };
+        Ok(data)
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        Ok(self.read_n_bytes::<1>()?[0])
+    }
+
+    fn read_float(&mut self) -> Result<f32> {
+        let data = self.read_n_bytes::<4>()?;
+        Ok(f32::from_le_bytes(data))
+    }
+    fn read_int(&mut self) -> Result<i32> {
+        let data = self.read_n_bytes::<4>()?;
+        Ok(i32::from_le_bytes(data))
+    }
+    /// Reads a length prefix of items at least `item_size` bytes long, which the game never
+    /// writes as negative.
+    fn read_count(&mut self, field: &'static str, item_size: u64, limit: usize) -> Result<usize> {
+        let count = self.read_int()?;
+        let count =
+            usize::try_from(count).map_err(|_| ParseError::NegativeCount { field, count })?;
+        self.check_count(field, count, item_size, limit)?;
+        Ok(count)
+    }
+
+    fn check_count(
+        &self,
+        field: &'static str,
+        count: usize,
+        item_size: u64,
+        limit: usize,
+    ) -> Result<()> {
+        let fits = self
+            .input_len
+            .map(|len| len.saturating_sub(self.reader.offset) / item_size)
+            .map_or(usize::MAX, |fits| fits.try_into().unwrap_or(usize::MAX));
+        let max = limit.min(fits);
+        if count > max {
+            return Err(ParseError::CountTooLarge { field, count, max });
+        }
+        Ok(())
+    }
+
+    fn preallocate(&self, count: usize) -> usize {
+        count.min(self.options.limits.max_preallocate)
+    }
+    fn read_state_id(&mut self) -> Result<StateId> {
+        let id = StateId(self.read_int()?);
+        self.highest_state_id = self.highest_state_id.max(id);
+        Ok(id)
+    }
+    fn read_address(&mut self) -> Result<Address> {
+        let data = self.read_n_bytes::<4>()?;
+        Ok(Address(u32::from_le_bytes(data)))
+    }
+    fn read_id(&mut self) -> Result<u16> {
+        let data = self.read_n_bytes::<2>()?;
+        Ok(u16::from_le_bytes(data))
+    }
+
+    /// Reads `count` bytes, growing the buffer as they arrive so a length the input can't back
+    /// doesn't allocate up front.
+    fn read_bytes(&mut self, count: usize) -> Result<Vec<u8>> {
+        let start = self.reader.offset;
+        let mut data = Vec::with_capacity(self.preallocate(count));
+        let mut chunk = [0u8; 4096];
+        while data.len() < count {
+            let len = (count - data.len()).min(chunk.len());
+            let read = self.reader.read_exact(&mut chunk[..len]);
+            // Point errors at the start of the value rather than the chunk that failed.
+            self.reader.last_read = start;
+            read?;
+            data.extend_from_slice(&chunk[..len]);
+        }
+        Ok(data)
+    }
+
+    fn read_n_bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut data = [0u8; N];
+        self.reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Text for bytes that aren't valid UTF-8, with each invalid byte turned into its own character
+/// from the last private use plane, so different bytes don't end up with the same text.
+fn escape_invalid_utf8(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        for &byte in chunk.invalid() {
+            text.push(char::from_u32(0x10_FF00 + u32::from(byte)).expect("a valid code point"));
+        }
+    }
+    text
+}
+
+/// A label's text, font size and color, `None` if the data doesn't have exactly that layout.
+fn parse_label(data: &[u8]) -> Option<CustomData> {
+    let (len, rest) = data.split_first_chunk::<4>()?;
+    let len = usize::try_from(i32::from_le_bytes(*len)).ok()?;
+    if rest.len() != len.checked_add(7)? {
+        return None;
+    }
+    let (text, rest) = rest.split_at(len);
+    let (font_size, &[r, g, b]) = rest.split_first_chunk::<4>()? else {
+        return None;
+    };
+    Some(CustomData::Label {
+        text: core::str::from_utf8(text).ok()?.into(),
+        font_size: u32::from_le_bytes(*font_size),
+        color: Color::from_rgb(r, g, b),
+    })
+}
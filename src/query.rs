@@ -0,0 +1,37 @@
+use alloc::vec::Vec;
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Component, CustomData};
+
+impl SaveFile {
+    pub fn find_components_by_type(&self, type_id: &str) -> Vec<&Component> {
+        self.components
+            .iter()
+            .filter(|comp| &*comp.id == type_id)
+            .collect()
+    }
+
+    pub fn component_type_counts(&self) -> HashMap<&str, usize> {
+        let mut counts = HashMap::new();
+        for comp in &self.components {
+            *counts.entry(&*comp.id).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    pub fn all_component_types(&self) -> HashSet<&str> {
+        self.components.iter().map(|comp| &*comp.id).collect()
+    }
+
+    /// The text of every label, in component order.
+    pub fn all_label_texts(&self) -> Vec<&str> {
+        self.components
+            .iter()
+            .filter_map(|comp| match &comp.custom_data {
+                CustomData::Label { text, .. } => Some(&**text),
+                _ => None,
+            })
+            .collect()
+    }
+}
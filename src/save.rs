@@ -0,0 +1,409 @@
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io::{BufReader, BufWriter};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "std")]
+use anyhow::Context;
+use anyhow::{anyhow, Error, Result};
+
+use crate::collections::{HashMap, IndexMap};
+use crate::error::ParseError;
+use crate::index::Index;
+use crate::parse::Parser;
+use crate::types::{Address, Component, SaveType, StateId, States, Version, Wire};
+use crate::write::Writer;
+
+/// The save layout version this crate reads and writes.
+pub const FORMAT_VERSION: u8 = 7;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaveFile {
+    /// Layout version byte, only ever something other than [`FORMAT_VERSION`] when parsed with
+    /// [`ParseOptions::allow_unknown_version`](crate::ParseOptions::allow_unknown_version).
+    pub format_version: u8,
+    pub game_version: Version,
+    pub save_type: SaveType,
+    pub mod_versions: HashMap<Box<str>, Version>,
+    pub comp_map: CompMap,
+    pub components: Vec<Component>,
+    pub wires: Vec<Wire>,
+    pub states: States,
+    pub highest_state_id: StateId,
+    pub highest_address: Address,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) index: Index,
+    /// Original bytes of strings that weren't valid UTF-8, keyed by the unique text they were
+    /// parsed as so writing the save reproduces them.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "HashMap::is_empty"))]
+    pub(crate) raw_strings: HashMap<Box<str>, Box<[u8]>>,
+    /// Mod names in the order the save listed them, so writing it back keeps that order.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub(crate) mod_order: Vec<Box<str>>,
+    /// State ids removing components left unused, handed out again before new ones.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) free_state_ids: Vec<StateId>,
+}
+
+// The index, the parsed mod order and the free state ids are bookkeeping, two saves with the
+// same contents are equal however they were built.
+impl PartialEq for SaveFile {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            format_version,
+            game_version,
+            save_type,
+            mod_versions,
+            comp_map,
+            components,
+            wires,
+            states,
+            highest_state_id,
+            highest_address,
+            index: _,
+            raw_strings,
+            mod_order: _,
+            free_state_ids: _,
+        } = self;
+        *format_version == other.format_version
+            && *game_version == other.game_version
+            && *save_type == other.save_type
+            && *mod_versions == other.mod_versions
+            && *comp_map == other.comp_map
+            && *components == other.components
+            && *wires == other.wires
+            && *states == other.states
+            && *highest_state_id == other.highest_state_id
+            && *highest_address == other.highest_address
+            && *raw_strings == other.raw_strings
+    }
+}
+
+/// A short multi-line summary of what the save contains.
+impl core::fmt::Display for SaveFile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Game version {}", self.game_version)?;
+        writeln!(f, "{} mods", self.mod_versions.len())?;
+        writeln!(f, "{} components", self.components.len())?;
+        let mut counts: Vec<_> = self.component_type_counts().into_iter().collect();
+        counts.sort_unstable();
+        for (id, count) in counts {
+            writeln!(f, "  {count} {id}")?;
+        }
+        writeln!(f, "{} wires", self.wires.len())?;
+        write!(f, "{} states", self.state_count())
+    }
+}
+
+impl SaveFile {
+    /// Creates a save with no components or wires, ready to be filled programmatically.
+    pub fn empty(game_version: Version) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            game_version,
+            save_type: SaveType::World,
+            mod_versions: HashMap::new(),
+            comp_map: CompMap::with_capacity(0),
+            components: Vec::new(),
+            wires: Vec::new(),
+            states: States(vec![0]),
+            highest_state_id: StateId(0),
+            highest_address: Address(1),
+            index: Index::default(),
+            raw_strings: HashMap::new(),
+            mod_order: Vec::new(),
+            free_state_ids: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+        let len = file
+            .metadata()
+            .with_context(|| format!("opening {}", path.display()))?
+            .len();
+        let save = Parser::new(BufReader::new(file))
+            .with_input_len(len)
+            .parse_save()
+            .with_context(|| format!("parsing {}", path.display()))?;
+        Ok(save)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Writer::new().write(self)
+    }
+
+    /// Serializes the whole save before touching `path`, so a failed write never truncates an
+    /// existing file.
+    #[cfg(feature = "std")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = self
+            .to_bytes()
+            .with_context(|| format!("serializing {}", path.display()))?;
+        fs::write(path, bytes).with_context(|| format!("writing {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Writes the save straight into a temporary file next to `path` without buffering all of
+    /// it in memory, then renames it over `path` once it is complete and synced.
+    ///
+    /// A failed write or a crash leaves the existing file at `path` untouched.
+    #[cfg(feature = "std")]
+    pub fn save_streamed(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.save_atomic(path, BackupPolicy::None)
+    }
+
+    /// Like [`SaveFile::save_streamed`], but first keeps copies of the file being replaced as
+    /// `path.bak.1`, `path.bak.2`, ... as `backups` asks, `.bak.1` being the newest.
+    #[cfg(feature = "std")]
+    pub fn save_atomic(&self, path: impl AsRef<Path>, backups: BackupPolicy) -> Result<()> {
+        let path = path.as_ref();
+        let tmp = with_suffix(path, ".tmp");
+
+        let result = self
+            .write_synced(&tmp)
+            .with_context(|| format!("writing {}", tmp.display()))
+            .and_then(|()| backups.rotate(path))
+            .and_then(|()| {
+                fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))
+            });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    #[cfg(feature = "std")]
+    fn write_synced(&self, path: &Path) -> Result<()> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        Writer::from_writer(&mut out).write_to(self)?;
+        let file = out.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Removes every component, wire and state, keeping the version and mod list.
+    ///
+    /// Counters go back to where [`SaveFile::empty`] starts them. Addresses are handed out after
+    /// `highest_address`, so with it at 1 nothing can ever get [`Address::ROOT`] (0).
+    pub fn clear_out(&mut self) {
+        self.comp_map = CompMap::with_capacity(0);
+        self.components.clear();
+        self.wires.clear();
+        self.states = States(vec![0]);
+        self.free_state_ids.clear();
+        self.index = Index::default();
+        self.highest_state_id = StateId(0);
+        self.highest_address = Address(1);
+    }
+
+    /// A copy of the save to restore after a destructive edit.
+    pub fn backup(&self) -> SaveFile {
+        self.clone()
+    }
+
+    /// Hands out an unused state id, turned off.
+    ///
+    /// Ids freed by [`SaveFile::remove_components`] are reused first, lowest first, otherwise
+    /// it is the id after `highest_state_id`. The states buffer grows to cover it however short
+    /// it was, and a bit left on past the highest used id, which parsed saves can have, is
+    /// cleared.
+    pub fn get_free_state_id(&mut self) -> StateId {
+        if let Some(id) = self.free_state_ids.pop() {
+            self.states.set(id, false);
+            return id;
+        }
+        self.highest_state_id.0 += 1;
+        self.states.set(self.highest_state_id, false);
+        self.highest_state_id
+    }
+    pub fn get_free_address(&mut self) -> Address {
+        self.highest_address.0 += 1;
+        self.highest_address
+    }
+}
+
+/// How many copies of the previous file [`SaveFile::save_atomic`] keeps.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupPolicy {
+    #[default]
+    None,
+    Keep(usize),
+}
+
+#[cfg(feature = "std")]
+impl BackupPolicy {
+    fn rotate(self, path: &Path) -> Result<()> {
+        let BackupPolicy::Keep(keep) = self else {
+            return Ok(());
+        };
+        if keep == 0 || !path.exists() {
+            return Ok(());
+        }
+
+        let backup = |n: usize| with_suffix(path, &format!(".bak.{n}"));
+        let oldest = backup(keep);
+        if oldest.exists() {
+            fs::remove_file(&oldest).with_context(|| format!("removing {}", oldest.display()))?;
+        }
+        for n in (1..keep).rev() {
+            let from = backup(n);
+            if from.exists() {
+                fs::rename(&from, backup(n + 1))
+                    .with_context(|| format!("rotating {}", from.display()))?;
+            }
+        }
+        fs::copy(path, backup(1)).with_context(|| format!("backing up {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+impl TryFrom<&[u8]> for SaveFile {
+    type Error = ParseError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, ParseError> {
+        Parser::from_bytes(bytes).parse_save()
+    }
+}
+
+impl TryFrom<Vec<u8>> for SaveFile {
+    type Error = ParseError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, ParseError> {
+        Self::try_from(bytes.as_slice())
+    }
+}
+
+impl TryFrom<SaveFile> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(save: SaveFile) -> Result<Self> {
+        save.to_bytes()
+    }
+}
+
+impl TryFrom<&SaveFile> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(save: &SaveFile) -> Result<Self> {
+        save.to_bytes()
+    }
+}
+
+/// The numeric ids the save uses for component type names.
+///
+/// Entries keep the order they were inserted or parsed in, which is the order they are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompMap {
+    pub(crate) entries: IndexMap<u16, Arc<str>>,
+}
+
+impl CompMap {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: IndexMap::with_capacity_and_hasher(capacity, Default::default()),
+        }
+    }
+
+    pub fn insert(&mut self, id: u16, name: Arc<str>) {
+        self.entries.insert(id, name);
+    }
+
+    pub fn get_id(&self, id: u16) -> Result<&Arc<str>> {
+        self.entries
+            .get(&id)
+            .ok_or_else(|| anyhow!("Missing id {id} in mapping"))
+    }
+
+    // Comp maps rarely have more than a few hundred entries, scanning them is fast enough.
+    pub fn get_name(&self, name: &str) -> Result<u16> {
+        self.find_name(name)
+            .ok_or_else(|| anyhow!("Missing {name} in mapping"))
+    }
+
+    /// Returns the numeric id of `name`, assigning the next free one if it isn't mapped yet.
+    pub fn ensure(&mut self, name: &str) -> u16 {
+        if let Some(id) = self.find_name(name) {
+            return id;
+        }
+        let new_id = self.entries.keys().max().unwrap_or(&0) + 1;
+        self.insert(new_id, name.into());
+        new_id
+    }
+
+    /// The `(numeric id, name)` pairs in ascending id order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        let mut entries: Vec<(u16, &str)> = self.in_order().collect();
+        entries.sort_unstable_by_key(|&(id, _)| id);
+        entries.into_iter()
+    }
+
+    /// The `(numeric id, name)` pairs in the order they were inserted.
+    pub(crate) fn in_order(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.entries.iter().map(|(&id, name)| (id, &**name))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_id(&self, id: u16) -> bool {
+        self.entries.contains_key(&id)
+    }
+
+    pub fn contains_name(&self, name: &str) -> bool {
+        self.find_name(name).is_some()
+    }
+
+    /// Removes `name` from the mapping, returning the numeric id it had.
+    pub fn remove(&mut self, name: &str) -> Option<u16> {
+        let id = self.find_name(name)?;
+        self.entries.shift_remove(&id);
+        Some(id)
+    }
+
+    fn find_name(&self, name: &str) -> Option<u16> {
+        self.entries
+            .iter()
+            .find(|(_, entry)| &***entry == name)
+            .map(|(&id, _)| id)
+    }
+}
+
+/// Entries are inserted in ascending id order, since a `HashMap` has none of its own.
+impl From<HashMap<u16, String>> for CompMap {
+    fn from(entries: HashMap<u16, String>) -> Self {
+        let mut entries: Vec<(u16, String)> = entries.into_iter().collect();
+        entries.sort_unstable_by_key(|&(id, _)| id);
+        let mut map = Self::with_capacity(entries.len());
+        for (id, name) in entries {
+            map.insert(id, name.into());
+        }
+        map
+    }
+}
@@ -0,0 +1,127 @@
+//! Serde support for the types that don't map onto their fields directly.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::collections::HashMap;
+use crate::index::Index;
+use crate::save::{CompMap, SaveFile};
+use crate::types::{Address, Component, SaveType, StateId, States, Version, Wire};
+
+// Deserialized through a copy of the fields so the index can be built afterwards.
+#[derive(Deserialize)]
+struct SaveFileFields {
+    format_version: u8,
+    game_version: Version,
+    save_type: SaveType,
+    mod_versions: HashMap<Box<str>, Version>,
+    comp_map: CompMap,
+    components: Vec<Component>,
+    wires: Vec<Wire>,
+    states: States,
+    highest_state_id: StateId,
+    highest_address: Address,
+    #[serde(default)]
+    raw_strings: HashMap<Box<str>, Box<[u8]>>,
+    #[serde(default)]
+    mod_order: Vec<Box<str>>,
+}
+
+impl SaveFile {
+    /// Compact JSON dump of the save, [`SaveFile::from_json`] reads it back unchanged.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string(self).map_err(anyhow::Error::msg)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<SaveFile> {
+        serde_json::from_str(json).map_err(anyhow::Error::msg)
+    }
+}
+
+impl<'de> Deserialize<'de> for SaveFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = SaveFileFields::deserialize(deserializer)?;
+        let mut save = SaveFile {
+            format_version: fields.format_version,
+            game_version: fields.game_version,
+            save_type: fields.save_type,
+            mod_versions: fields.mod_versions,
+            comp_map: fields.comp_map,
+            components: fields.components,
+            wires: fields.wires,
+            states: fields.states,
+            highest_state_id: fields.highest_state_id,
+            highest_address: fields.highest_address,
+            index: Index::default(),
+            raw_strings: fields.raw_strings,
+            mod_order: fields.mod_order,
+            free_state_ids: Vec::new(),
+        };
+        save.rebuild_index();
+        Ok(save)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompMapEntry {
+    id: u16,
+    name: Arc<str>,
+}
+
+/// A list of `{id, name}` entries in the map's order.
+impl Serialize for CompMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<CompMapEntry> = self
+            .entries
+            .iter()
+            .map(|(&id, name)| CompMapEntry {
+                id,
+                name: name.clone(),
+            })
+            .collect();
+        entries.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<CompMapEntry>::deserialize(deserializer)?;
+        let mut comp_map = CompMap::with_capacity(entries.len());
+        for entry in entries {
+            comp_map.insert(entry.id, entry.name);
+        }
+        Ok(comp_map)
+    }
+}
+
+/// A lowercase hex string, two digits per byte.
+impl Serialize for States {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(self.0.len() * 2);
+        for byte in &self.0 {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        serializer.serialize_str(&hex)
+    }
+}
+
+impl<'de> Deserialize<'de> for States {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(D::Error::custom("states must be pairs of hex digits"));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(D::Error::custom)?;
+        Ok(States(bytes))
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::{anyhow, bail, Result};
+
+use crate::save::SaveFile;
+use crate::types::{Address, CustomData, StateId};
+
+impl SaveFile {
+    pub fn get_state(&self, id: StateId) -> Result<bool> {
+        if !self.state_in_range(id) {
+            bail!("State id {id} is outside of the states buffer");
+        }
+        Ok(self.states.get_bit(id))
+    }
+
+    pub fn set_state(&mut self, id: StateId, on: bool) -> Result<()> {
+        if !self.state_in_range(id) {
+            bail!("State id {id} is outside of the states buffer");
+        }
+        self.states.set(id, on);
+        Ok(())
+    }
+
+    /// Turns a switch on or off, updating both its custom data and its output state so it
+    /// renders and behaves the same.
+    pub fn set_switch_on(&mut self, address: Address, on: bool) -> Result<()> {
+        let comp = self
+            .find_component_mut(address)
+            .ok_or_else(|| anyhow!("No component with address {address}"))?;
+        let CustomData::Switch { on: switch_on, .. } = &mut comp.custom_data else {
+            bail!("Component {address} is a {}, not a switch", comp.id);
+        };
+        let Some(&output) = comp.outputs.first() else {
+            bail!("Switch {address} has no output");
+        };
+        if output.0 < 0 {
+            bail!("Switch {address} has negative output state id {output}");
+        }
+        *switch_on = on;
+        self.states.set(output, on);
+        Ok(())
+    }
+
+    pub fn is_switch_on(&self, address: Address) -> Result<bool> {
+        let comp = self
+            .find_component(address)
+            .ok_or_else(|| anyhow!("No component with address {address}"))?;
+        match comp.custom_data {
+            CustomData::Switch { on, .. } => Ok(on),
+            _ => bail!("Component {address} is a {}, not a switch", comp.id),
+        }
+    }
+
+    /// Turns every state off, keeping the size of the buffer.
+    pub fn reset_all_states(&mut self) {
+        self.states.0.fill(0);
+    }
+
+    /// How many state ids fit in the states buffer.
+    pub fn state_count(&self) -> usize {
+        self.states.len_bits()
+    }
+
+    pub(crate) fn state_in_range(&self, id: StateId) -> bool {
+        self.states.get(id).is_some()
+    }
+}
@@ -0,0 +1,73 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::collections::HashMap;
+use crate::save::SaveFile;
+use crate::types::{Address, Version};
+
+/// Counts describing a save, see [`SaveFile::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveStats {
+    pub component_count: usize,
+    pub wire_count: usize,
+    /// How many state ids fit in the states buffer.
+    pub state_count: usize,
+    /// How many states are on.
+    pub active_state_count: usize,
+    pub component_types: HashMap<String, usize>,
+    pub mod_count: usize,
+    pub game_version: Version,
+    pub highest_address: Address,
+}
+
+impl SaveFile {
+    pub fn stats(&self) -> SaveStats {
+        SaveStats {
+            component_count: self.components.len(),
+            wire_count: self.wires.len(),
+            state_count: self.state_count(),
+            active_state_count: self
+                .states
+                .0
+                .iter()
+                .map(|byte| byte.count_ones() as usize)
+                .sum(),
+            component_types: self
+                .component_type_counts()
+                .into_iter()
+                .map(|(id, count)| (id.into(), count))
+                .collect(),
+            mod_count: self.mod_versions.len(),
+            game_version: self.game_version.clone(),
+            highest_address: self.highest_address,
+        }
+    }
+}
+
+/// A two column table, component types sorted from most to least common.
+impl fmt::Display for SaveStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut types: Vec<(&String, &usize)> = self.component_types.iter().collect();
+        types.sort_unstable_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let width = types
+            .iter()
+            .map(|(id, _)| id.len() + 2)
+            .fold(16, usize::max);
+
+        writeln!(f, "{:width$} {}", "Game version", self.game_version)?;
+        writeln!(f, "{:width$} {}", "Components", self.component_count)?;
+        writeln!(f, "{:width$} {}", "Wires", self.wire_count)?;
+        writeln!(
+            f,
+            "{:width$} {} ({} on)",
+            "States", self.state_count, self.active_state_count
+        )?;
+        writeln!(f, "{:width$} {}", "Mods", self.mod_count)?;
+        write!(f, "{:width$} {}", "Highest address", self.highest_address)?;
+        for (id, count) in types {
+            write!(f, "\n  {:w$} {count}", id, w = width - 2)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::collections::HashSet;
+use crate::save::SaveFile;
+use crate::types::{Address, Axis, BoundingBox, Component, CustomData, Vec3};
+
+impl SaveFile {
+    /// Tight box around every component position, `None` for a save without components.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let (first, rest) = self.components.split_first()?;
+        let mut bounds = BoundingBox {
+            min: first.position,
+            max: first.position,
+        };
+        for comp in rest {
+            let p = comp.position;
+            bounds.min = Vec3 {
+                x: bounds.min.x.min(p.x),
+                y: bounds.min.y.min(p.y),
+                z: bounds.min.z.min(p.z),
+            };
+            bounds.max = Vec3 {
+                x: bounds.max.x.max(p.x),
+                y: bounds.max.y.max(p.y),
+                z: bounds.max.z.max(p.z),
+            };
+        }
+        Some(bounds)
+    }
+
+    pub fn components_in_region(&self, region: &BoundingBox) -> Vec<&Component> {
+        self.components
+            .iter()
+            .filter(|comp| region.contains(&comp.position))
+            .collect()
+    }
+
+    /// Moves the components in `addresses`, or every component if `None`, by `offset`.
+    ///
+    /// Nothing is moved if any of the new positions would overflow.
+    pub fn translate_components(
+        &mut self,
+        offset: Vec3,
+        addresses: Option<&[Address]>,
+    ) -> Result<()> {
+        let selected: Option<HashSet<Address>> =
+            addresses.map(|addresses| addresses.iter().copied().collect());
+        let is_selected =
+            |address: &Address| selected.as_ref().is_none_or(|set| set.contains(address));
+
+        let moved = self
+            .components
+            .iter()
+            .filter(|comp| is_selected(&comp.address))
+            .map(|comp| {
+                comp.position
+                    .checked_add(&offset)
+                    .ok_or_else(|| anyhow!("Translating component {} overflows", comp.address))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let targets = self
+            .components
+            .iter_mut()
+            .filter(|comp| is_selected(&comp.address));
+        for (comp, position) in targets.zip(moved) {
+            comp.position = position;
+        }
+
+        Ok(())
+    }
+
+    /// Copies a component `offset` away from the original, returning the copy's address.
+    ///
+    /// The copy keeps the parent and custom data but gets fresh state ids, so it starts without
+    /// wires. Its outputs start in the same state as the original's, so a switch that is on stays
+    /// on, and its inputs start off.
+    pub fn duplicate_component(&mut self, address: Address, offset: Vec3) -> Result<Address> {
+        let comp = self
+            .find_component(address)
+            .ok_or_else(|| anyhow!("No component with address {address}"))?;
+        let position = comp
+            .position
+            .checked_add(&offset)
+            .ok_or_else(|| anyhow!("Duplicating component {address} overflows"))?;
+        let mut copy = comp.clone();
+
+        copy.address = self.get_free_address();
+        copy.position = position;
+        for state_id in &mut copy.inputs {
+            *state_id = self.get_free_state_id();
+        }
+        for state_id in &mut copy.outputs {
+            let on = self.states.get_bit(*state_id);
+            *state_id = self.get_free_state_id();
+            self.states.set(*state_id, on);
+        }
+        let new_address = copy.address;
+        self.push_component(copy);
+        Ok(new_address)
+    }
+
+    /// Moves every component so the lowest corner of the bounding box is at the origin,
+    /// returning the offset that was added.
+    ///
+    /// Nothing is moved if the save is too wide for the new positions or the offset to fit.
+    pub fn normalize_positions(&mut self) -> Result<Vec3> {
+        let Some(bounds) = self.bounding_box() else {
+            return Ok(Vec3::zero());
+        };
+        let offset = Vec3::zero()
+            .checked_sub(&bounds.min)
+            .ok_or_else(|| anyhow!("Offset to the origin overflows"))?;
+        let moved = self
+            .components
+            .iter()
+            .map(|comp| {
+                comp.position
+                    .checked_sub(&bounds.min)
+                    .ok_or_else(|| anyhow!("Normalizing component {} overflows", comp.address))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (comp, position) in self.components.iter_mut().zip(moved) {
+            comp.position = position;
+        }
+        Ok(offset)
+    }
+
+    /// Mirrors every component across the plane through the origin perpendicular to `axis`.
+    ///
+    /// Flipping twice along the same axis restores the save. Fails without changing anything if
+    /// a component sits at the lowest coordinate, which has no mirror image.
+    pub fn flip(&mut self, axis: Axis) -> Result<()> {
+        for comp in &self.components {
+            let p = &comp.position;
+            let coordinate = match axis {
+                Axis::X => p.x,
+                Axis::Y => p.y,
+                Axis::Z => p.z,
+            };
+            if coordinate.checked_neg().is_none() {
+                bail!("Flipping component {} overflows", comp.address);
+            }
+        }
+        for comp in &mut self.components {
+            let (p, r) = (&mut comp.position, &mut comp.rotation);
+            match axis {
+                Axis::X => (p.x, r.y, r.z) = (-p.x, -r.y, -r.z),
+                Axis::Y => (p.y, r.x, r.z) = (-p.y, -r.x, -r.z),
+                Axis::Z => (p.z, r.x, r.y) = (-p.z, -r.x, -r.y),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_delayer_tick(&mut self, address: Address, ticks: u32) -> Result<()> {
+        let comp = self
+            .find_component_mut(address)
+            .ok_or_else(|| anyhow!("No component with address {address}"))?;
+        if &*comp.id != "MHG.Delayer" {
+            bail!("Component {address} is a {}, not a delayer", comp.id);
+        }
+        comp.custom_data = CustomData::Delayer { ticks };
+        Ok(())
+    }
+
+    /// Changes every `from` component into a `to` component, returning how many changed.
+    ///
+    /// The `from` entry stays in the component map so numeric ids remain stable.
+    pub fn replace_component_type(&mut self, from: &str, to: &str) -> usize {
+        let to: Arc<str> = to.into();
+        let mut count = 0;
+        for comp in &mut self.components {
+            if &*comp.id == from {
+                comp.id = to.clone();
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.comp_map.ensure(&to);
+        }
+        count
+    }
+}
@@ -0,0 +1,626 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, Result};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub u32);
+impl Address {
+    /// Parent of components that sit directly in the world.
+    pub const ROOT: Address = Address(0);
+}
+impl core::fmt::Display for Address {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateId(pub i32);
+impl core::fmt::Display for StateId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Ordered field by field, like major, minor, patch and build numbers.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub i32, pub i32, pub i32, pub i32);
+
+impl Version {
+    /// Whether this is at least `minimum`, ignoring the build number.
+    pub fn is_compatible_with(&self, minimum: &Version) -> bool {
+        (self.0, self.1, self.2) >= (minimum.0, minimum.1, minimum.2)
+    }
+}
+
+/// Parses the `1.2.3.4` format [`Version`] is displayed in.
+impl core::str::FromStr for Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('.').map(|part| {
+            part.parse::<i32>()
+                .map_err(|_| anyhow!("Invalid version '{s}'"))
+        });
+        let mut next = || {
+            parts
+                .next()
+                .unwrap_or_else(|| Err(anyhow!("Invalid version '{s}'")))
+        };
+        let version = Version(next()?, next()?, next()?, next()?);
+        if parts.next().is_some() {
+            return Err(anyhow!("Invalid version '{s}'"));
+        }
+        Ok(version)
+    }
+}
+
+impl core::fmt::Debug for Version {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Version({self})")
+    }
+}
+
+impl core::fmt::Display for Version {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.0, self.1, self.2, self.3)
+    }
+}
+
+/// Integer position of a component.
+///
+/// The arithmetic operators are plain `i32` arithmetic, so they panic on overflow in debug builds
+/// and wrap in release builds. Use [`Vec3::checked_add`] when the ranges aren't known to be safe.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Vec3 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+/// Parses `x,y,z`, the form command line offsets are given in.
+impl core::str::FromStr for Vec3 {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split(',').map(|part| {
+            part.trim()
+                .parse::<i32>()
+                .map_err(|_| anyhow!("Invalid position '{s}'"))
+        });
+        let mut next = || {
+            parts
+                .next()
+                .unwrap_or_else(|| Err(anyhow!("Invalid position '{s}'")))
+        };
+        let position = Vec3 {
+            x: next()?,
+            y: next()?,
+            z: next()?,
+        };
+        if parts.next().is_some() {
+            return Err(anyhow!("Invalid position '{s}'"));
+        }
+        Ok(position)
+    }
+}
+
+impl core::fmt::Debug for Vec3 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl Vec3 {
+    pub fn zero() -> Vec3 {
+        Vec3 { x: 0, y: 0, z: 0 }
+    }
+
+    /// Saturates at `i64::MAX`, which only positions about three billion apart reach.
+    pub fn distance_squared(&self, other: &Vec3) -> i64 {
+        let dx = self.x as i128 - other.x as i128;
+        let dy = self.y as i128 - other.y as i128;
+        let dz = self.z as i128 - other.z as i128;
+        i64::try_from(dx * dx + dy * dy + dz * dz).unwrap_or(i64::MAX)
+    }
+
+    pub fn checked_add(&self, other: &Vec3) -> Option<Vec3> {
+        Some(Vec3 {
+            x: self.x.checked_add(other.x)?,
+            y: self.y.checked_add(other.y)?,
+            z: self.z.checked_add(other.z)?,
+        })
+    }
+
+    pub fn checked_sub(&self, other: &Vec3) -> Option<Vec3> {
+        Some(Vec3 {
+            x: self.x.checked_sub(other.x)?,
+            y: self.y.checked_sub(other.y)?,
+            z: self.z.checked_sub(other.z)?,
+        })
+    }
+}
+
+impl core::ops::Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl core::ops::Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl core::ops::Mul<i32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, scale: i32) -> Vec3 {
+        Vec3 {
+            x: self.x * scale,
+            y: self.y * scale,
+            z: self.z * scale,
+        }
+    }
+}
+
+impl core::ops::Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// Axis aligned box, both corners are inclusive.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, p: &Vec3) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+
+    /// `max - min` on every axis, saturating at `i32::MAX` for boxes wider than that.
+    pub fn size(&self) -> Vec3 {
+        Vec3 {
+            x: self.max.x.saturating_sub(self.min.x),
+            y: self.max.y.saturating_sub(self.min.y),
+            z: self.max.z.saturating_sub(self.min.z),
+        }
+    }
+
+    pub fn center_f32(&self) -> (f32, f32, f32) {
+        let center = |min: i32, max: i32| (min as f32 + max as f32) / 2.;
+        (
+            center(self.min.x, self.max.x),
+            center(self.min.y, self.max.y),
+            center(self.min.z, self.max.z),
+        )
+    }
+
+    /// Grows the box by `margin` on every side, stopping at the edges of the `i32` grid.
+    pub fn expand(&self, margin: i32) -> BoundingBox {
+        let (min, max) = (self.min, self.max);
+        BoundingBox {
+            min: Vec3 {
+                x: min.x.saturating_sub(margin),
+                y: min.y.saturating_sub(margin),
+                z: min.z.saturating_sub(margin),
+            },
+            max: Vec3 {
+                x: max.x.saturating_add(margin),
+                y: max.y.saturating_add(margin),
+                z: max.z.saturating_add(margin),
+            },
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+impl core::fmt::Debug for Quat {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "({}, {}, {}, {})", self.x, self.y, self.z, self.w)
+    }
+}
+
+impl PartialEq for Quat {
+    fn eq(&self, other: &Self) -> bool {
+        (self.x - other.x).abs() < f32::EPSILON
+            && (self.y - other.y).abs() < f32::EPSILON
+            && (self.z - other.z).abs() < f32::EPSILON
+            && (self.w - other.w).abs() < f32::EPSILON
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+        w: 1.,
+    };
+
+    pub fn identity() -> Quat {
+        Quat::IDENTITY
+    }
+
+    /// Hamilton product, the result applies `other` first and then `self`.
+    pub fn multiply(&self, other: &Quat) -> Quat {
+        Quat {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+
+    pub fn conjugate(&self) -> Quat {
+        Quat {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: self.w,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Scales the quaternion to unit length, a zero quaternion becomes the identity.
+    pub fn normalize(&self) -> Quat {
+        let length = (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt();
+        if length == 0. {
+            return Quat::identity();
+        }
+        Quat {
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+            w: self.w / length,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_axis_angle(axis: Vec3f, radians: f32) -> Quat {
+        let Vec3f(x, y, z) = axis.normalize();
+        let (sin, cos) = (radians / 2.).sin_cos();
+        Quat {
+            x: x * sin,
+            y: y * sin,
+            z: z * sin,
+            w: cos,
+        }
+    }
+
+    pub fn rotate_vec3(&self, v: Vec3f) -> Vec3f {
+        let Vec3f(x, y, z) = v;
+        let rotated = self
+            .multiply(&Quat { x, y, z, w: 0. })
+            .multiply(&self.conjugate());
+        Vec3f(rotated.x, rotated.y, rotated.z)
+    }
+}
+
+/// Floating point vector used for rotation math, positions themselves are always integers.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vec3f(pub f32, pub f32, pub f32);
+
+impl Vec3f {
+    #[cfg(feature = "std")]
+    pub fn length(&self) -> f32 {
+        (self.0 * self.0 + self.1 * self.1 + self.2 * self.2).sqrt()
+    }
+
+    #[cfg(feature = "std")]
+    /// Scales the vector to unit length, a zero vector is returned unchanged.
+    pub fn normalize(&self) -> Vec3f {
+        let length = self.length();
+        if length == 0. {
+            return *self;
+        }
+        Vec3f(self.0 / length, self.1 / length, self.2 / length)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+impl core::fmt::Display for Color {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+impl Color {
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        (self.r, self.g, self.b)
+    }
+
+    /// Parses `#RRGGBB`, the leading `#` is optional.
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(anyhow!("Invalid hex color '{s}'"));
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| anyhow!("Invalid hex color '{s}'"))
+        };
+        Ok(Self::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Linearly interpolates towards `other`, `t` is clamped to `0.0..=1.0`.
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let t = t.clamp(0., 1.);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t + 0.5) as u8;
+        Self::from_rgb(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomData {
+    /// No custom data object at all, stored as a length of -1 unlike an empty `Unknown`.
+    None,
+    Unknown(Vec<u8>),
+    Switch {
+        color: Color,
+        on: bool,
+    },
+    Display {
+        // never seems to go above 16, but I assume they are using a C# int?
+        color_mode: u32,
+    },
+    Delayer {
+        ticks: u32,
+    },
+    /// Stored as the length prefixed text, then the font size and the color's rgb bytes.
+    Label {
+        text: Box<str>,
+        font_size: u32,
+        color: Color,
+    },
+}
+
+impl CustomData {
+    /// Whether this kind of custom data is what the game expects for component type `id`.
+    pub fn fits_component(&self, id: &str) -> bool {
+        match self {
+            CustomData::None | CustomData::Unknown(_) => true,
+            CustomData::Switch { .. } => matches!(id, "MHG.Switch" | "MHG.Button"),
+            CustomData::Display { .. } => id == "MHG.StandingDisplay",
+            CustomData::Delayer { .. } => id == "MHG.Delayer",
+            CustomData::Label { .. } => id == "MHG.Label",
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub address: Address,
+    pub parent: Address,
+    pub id: Arc<str>,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub inputs: Vec<StateId>,
+    pub outputs: Vec<StateId>,
+    pub custom_data: CustomData,
+}
+
+impl core::fmt::Display for Component {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Vec3 { x, y, z } = self.position;
+        write!(f, "[{}] {} @ ({x},{y},{z})", self.address, self.id)
+    }
+}
+
+/// What kind of file a save is, subassemblies use the same layout as worlds.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveType {
+    World,
+    Subassembly,
+}
+
+impl SaveType {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::World),
+            2 => Some(Self::Subassembly),
+            _ => None,
+        }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::World => 1,
+            Self::Subassembly => 2,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PegType {
+    Input,
+    Output,
+}
+
+impl core::fmt::Display for PegType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Input => write!(f, "input"),
+            Self::Output => write!(f, "output"),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PegAddress {
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub type_: PegType,
+    pub component: Address,
+    pub index: i32,
+}
+
+impl From<(u32, PegType, i32)> for PegAddress {
+    fn from((component, type_, index): (u32, PegType, i32)) -> Self {
+        Self {
+            type_,
+            component: Address(component),
+            index,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Wire {
+    pub start: PegAddress,
+    pub end: PegAddress,
+    pub state_id: StateId,
+    pub rotation: f32,
+}
+
+impl PartialEq for Wire {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+            && self.end == other.end
+            && self.state_id == other.state_id
+            && (self.rotation - other.rotation).abs() < f32::EPSILON
+    }
+}
+
+impl core::fmt::Display for Wire {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}:{}({}) → {}:{}",
+            self.start.component,
+            self.start.index,
+            self.start.type_,
+            self.end.component,
+            self.end.index
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct States(pub Vec<u8>);
+impl core::fmt::Debug for States {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "[...]")
+    }
+}
+
+/// The on/off state of every state id, packed eight to a byte with the lowest state id in the
+/// least significant bit, the way the game stores them.
+impl States {
+    /// Whether state id `id` is on, `None` if it is past the end of the buffer.
+    pub fn get(&self, StateId(id): StateId) -> Option<bool> {
+        let index = usize::try_from(id).ok()?;
+        self.0
+            .get(index / 8)
+            .map(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Turns state id `id` on or off, growing the buffer if it is too short.
+    ///
+    /// # Panics
+    /// If `id` is negative.
+    pub fn set(&mut self, StateId(id): StateId, on: bool) {
+        let index = usize::try_from(id).expect("state ids are not negative");
+        let byte = index / 8;
+        if byte >= self.0.len() {
+            self.0.resize(byte + 1, 0);
+        }
+        if on {
+            self.0[byte] |= 1 << (index % 8);
+        } else {
+            self.0[byte] &= !(1 << (index % 8));
+        }
+    }
+
+    /// How many state ids fit in the buffer.
+    pub fn len_bits(&self) -> usize {
+        self.0.len() * 8
+    }
+
+    /// The state ids that are on, in ascending order.
+    pub fn iter_on(&self) -> impl Iterator<Item = StateId> + '_ {
+        self.0.iter().enumerate().flat_map(|(byte_index, &byte)| {
+            (0..8)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| StateId((byte_index * 8 + bit) as i32))
+        })
+    }
+
+    pub(crate) fn get_bit(&self, id: StateId) -> bool {
+        self.get(id).unwrap_or(false)
+    }
+}
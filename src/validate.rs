@@ -0,0 +1,239 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::collections::{HashMap, HashSet};
+use crate::save::SaveFile;
+use crate::types::{Address, Component, PegAddress, PegType, StateId};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    DuplicateAddress {
+        address: Address,
+    },
+    DanglingWire {
+        wire: usize,
+        address: Address,
+    },
+    PegIndexOutOfRange {
+        wire: usize,
+        address: Address,
+        index: i32,
+    },
+    ComponentStateOutOfRange {
+        address: Address,
+        state_id: StateId,
+    },
+    WireStateOutOfRange {
+        wire: usize,
+        state_id: StateId,
+    },
+    MissingParent {
+        address: Address,
+        parent: Address,
+    },
+    SelfParent {
+        address: Address,
+    },
+    /// Components that are each other's parents, in parent order.
+    ParentCycle {
+        addresses: Vec<Address>,
+    },
+}
+
+/// How bad a [`ValidationError`] is.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The writer fixes it up, the save can still be written as is.
+    Warning,
+    /// The game will refuse or mangle the save.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl ValidationError {
+    pub fn severity(&self) -> Severity {
+        match self {
+            // The writer pads the states buffer to cover every used state id.
+            Self::ComponentStateOutOfRange { .. } | Self::WireStateOutOfRange { .. } => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateAddress { address } => {
+                write!(f, "Address {address} is used by more than one component")
+            }
+            Self::DanglingWire { wire, address } => {
+                write!(f, "Wire #{wire} references missing component {address}")
+            }
+            Self::PegIndexOutOfRange {
+                wire,
+                address,
+                index,
+            } => write!(
+                f,
+                "Wire #{wire} references peg {index} which component {address} does not have"
+            ),
+            Self::ComponentStateOutOfRange { address, state_id } => write!(
+                f,
+                "Component {address} uses state id {state_id} outside of the states buffer"
+            ),
+            Self::WireStateOutOfRange { wire, state_id } => write!(
+                f,
+                "Wire #{wire} uses state id {state_id} outside of the states buffer"
+            ),
+            Self::MissingParent { address, parent } => {
+                write!(f, "Component {address} has missing parent {parent}")
+            }
+            Self::SelfParent { address } => write!(f, "Component {address} is its own parent"),
+            Self::ParentCycle { addresses } => {
+                write!(f, "Components form a parent cycle:")?;
+                for address in addresses {
+                    write!(f, " {address} ->")?;
+                }
+                write!(f, " {}", addresses[0])
+            }
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+impl SaveFile {
+    /// Checks the save for internal inconsistencies the game would choke on, collecting every
+    /// problem instead of stopping at the first one.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        let mut by_address: HashMap<Address, &Component> =
+            HashMap::with_capacity(self.components.len());
+        let mut duplicates = HashSet::new();
+        for comp in &self.components {
+            if by_address.insert(comp.address, comp).is_some() && duplicates.insert(comp.address) {
+                errors.push(ValidationError::DuplicateAddress {
+                    address: comp.address,
+                });
+            }
+        }
+
+        for comp in &self.components {
+            if comp.parent != Address::ROOT && !by_address.contains_key(&comp.parent) {
+                errors.push(ValidationError::MissingParent {
+                    address: comp.address,
+                    parent: comp.parent,
+                });
+            }
+            for &state_id in comp.inputs.iter().chain(&comp.outputs) {
+                if !self.state_in_range(state_id) {
+                    errors.push(ValidationError::ComponentStateOutOfRange {
+                        address: comp.address,
+                        state_id,
+                    });
+                }
+            }
+        }
+
+        errors.extend(parent_cycles(&self.components, &by_address));
+        errors.extend(self.wire_errors(&by_address));
+        for (index, wire) in self.wires.iter().enumerate() {
+            if !self.state_in_range(wire.state_id) {
+                errors.push(ValidationError::WireStateOutOfRange {
+                    wire: index,
+                    state_id: wire.state_id,
+                });
+            }
+        }
+
+        errors
+    }
+
+    /// Checks only that every wire end is on a peg of an existing component.
+    pub fn validate_wires(&self) -> Vec<ValidationError> {
+        let by_address: HashMap<Address, &Component> = self
+            .components
+            .iter()
+            .map(|comp| (comp.address, comp))
+            .collect();
+        self.wire_errors(&by_address)
+    }
+
+    fn wire_errors(&self, by_address: &HashMap<Address, &Component>) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for (index, wire) in self.wires.iter().enumerate() {
+            for peg in [&wire.start, &wire.end] {
+                match by_address.get(&peg.component) {
+                    None => errors.push(ValidationError::DanglingWire {
+                        wire: index,
+                        address: peg.component,
+                    }),
+                    Some(comp) if !peg_in_range(comp, peg) => {
+                        errors.push(ValidationError::PegIndexOutOfRange {
+                            wire: index,
+                            address: peg.component,
+                            index: peg.index,
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_empty()
+    }
+}
+
+/// Follows the parent chain of every component, reporting each cycle once.
+fn parent_cycles(
+    components: &[Component],
+    by_address: &HashMap<Address, &Component>,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let mut done = HashSet::new();
+    for comp in components {
+        let mut path = Vec::new();
+        let mut current = comp.address;
+        while current != Address::ROOT && !done.contains(&current) {
+            if let Some(position) = path.iter().position(|&address| address == current) {
+                let cycle: Vec<Address> = path[position..].to_vec();
+                errors.push(match cycle[..] {
+                    [address] => ValidationError::SelfParent { address },
+                    _ => ValidationError::ParentCycle { addresses: cycle },
+                });
+                break;
+            }
+            let Some(comp) = by_address.get(&current) else {
+                break;
+            };
+            path.push(current);
+            current = comp.parent;
+        }
+        done.extend(path);
+    }
+    errors
+}
+
+fn peg_in_range(comp: &Component, peg: &PegAddress) -> bool {
+    let count = match peg.type_ {
+        PegType::Input => comp.inputs.len(),
+        PegType::Output => comp.outputs.len(),
+    };
+    peg.index >= 0 && (peg.index as usize) < count
+}
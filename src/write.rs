@@ -0,0 +1,424 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::collections::{HashMap, HashSet};
+use crate::error::{WriteError, WriteIssue};
+use crate::io::Sink;
+use crate::save::SaveFile;
+use crate::types::{Address, Component, CustomData, PegAddress, PegType, StateId, Version, Wire};
+
+pub struct Writer<W: Sink = Vec<u8>> {
+    out: W,
+    validate: bool,
+}
+
+impl Default for Writer<Vec<u8>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Writer<Vec<u8>> {
+    pub fn new() -> Self {
+        Writer::from_writer(Vec::new())
+    }
+
+    pub fn write(mut self, save: &SaveFile) -> Result<Vec<u8>> {
+        self.out.reserve(save.serialized_size_estimate());
+        self.write_to(save)?;
+        Ok(self.out)
+    }
+
+    /// Checks that `save` can be written completely, collecting every problem.
+    ///
+    /// State ids past the end of the states buffer aren't a problem, the writer pads it.
+    pub fn validate(save: &SaveFile) -> Result<(), Vec<WriteIssue>> {
+        let mut issues = Vec::new();
+        let mut check_len = |what, len| {
+            if let Err(err) = checked_len(what, len) {
+                issues.push(WriteIssue::TooLarge(err));
+            }
+        };
+        check_len("component count", save.components.len());
+        check_len("wire count", save.wires.len());
+        check_len("mod version count", save.mod_versions.len());
+        check_len("component map size", save.comp_map.len());
+        check_len("states length", save.states.0.len());
+        for name in save.mod_versions.keys() {
+            check_len("string length", name.len());
+        }
+        for (_, name) in save.comp_map.iter() {
+            check_len("string length", name.len());
+        }
+        for comp in &save.components {
+            check_len("input count", comp.inputs.len());
+            check_len("output count", comp.outputs.len());
+            match &comp.custom_data {
+                CustomData::Unknown(data) => check_len("custom data length", data.len()),
+                CustomData::Label { text, .. } => check_len("label length", text.len()),
+                _ => {}
+            }
+        }
+
+        let names: HashSet<&str> = save.comp_map.in_order().map(|(_, name)| name).collect();
+        for comp in &save.components {
+            if !names.contains(&*comp.id) {
+                issues.push(WriteIssue::MissingCompMapEntry {
+                    address: comp.address,
+                    id: comp.id.clone(),
+                });
+            }
+        }
+        let mut addresses = HashSet::with_capacity(save.components.len());
+        let mut duplicates = HashSet::new();
+        for comp in &save.components {
+            if !addresses.insert(comp.address) && duplicates.insert(comp.address) {
+                issues.push(WriteIssue::DuplicateAddress {
+                    address: comp.address,
+                });
+            }
+        }
+        for (index, wire) in save.wires.iter().enumerate() {
+            for peg in [&wire.start, &wire.end] {
+                if !addresses.contains(&peg.component) {
+                    issues.push(WriteIssue::DanglingWire {
+                        wire: index,
+                        address: peg.component,
+                    });
+                }
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+impl<W: Sink> Writer<W> {
+    /// Creates a writer that streams the save into `out` instead of buffering it, pass `&mut out`
+    /// to keep ownership of the sink.
+    pub fn from_writer(out: W) -> Self {
+        Writer {
+            out,
+            validate: true,
+        }
+    }
+
+    /// Skips [`Writer::validate`], writing whatever the save contains as far as possible.
+    pub fn without_validation(mut self) -> Self {
+        self.validate = false;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    pub fn write_to(&mut self, save: &SaveFile) -> Result<()> {
+        if self.validate {
+            if let Err(issues) = Writer::validate(save) {
+                match issues.len() {
+                    1 => bail!("Can not write save, {}", issues[0]),
+                    count => bail!("Can not write save, {} (+{} more)", issues[0], count - 1),
+                }
+            }
+        }
+
+        self.write_header(save).context("writing header")?;
+
+        self.write_mod_versions(save)
+            .context("writing mod versions")?;
+        self.write_comp_map(save).context("writing component map")?;
+
+        // Looked up once per component, so don't scan the comp map every time.
+        let mut ids = HashMap::with_capacity(save.comp_map.len());
+        for (id, name) in save.comp_map.in_order() {
+            ids.entry(name).or_insert(id);
+        }
+        for (index, comp) in save.components.iter().enumerate() {
+            self.write_component(comp, &ids)
+                .with_context(|| format!("writing component #{index}"))?;
+        }
+        for (index, wire) in save.wires.iter().enumerate() {
+            self.write_wire(wire)
+                .with_context(|| format!("writing wire #{index}"))?;
+        }
+
+        self.write_states(save).context("writing states")?;
+
+        self.write_raw_string("redstone sux lol")
+            .context("writing footer")?;
+        self.out.flush_bytes().context("flushing output")?;
+
+        Ok(())
+    }
+
+    /// Pads the states with zeros if the save uses state ids past the end of the buffer, so
+    /// the file the game gets is consistent.
+    fn write_states(&mut self, save: &SaveFile) -> Result<()> {
+        let used = save
+            .components
+            .iter()
+            .flat_map(|comp| comp.inputs.iter().chain(&comp.outputs))
+            .chain(save.wires.iter().map(|wire| &wire.state_id))
+            .chain((save.highest_state_id.0 > 0).then_some(&save.highest_state_id))
+            .max();
+        let needed = used.map_or(0, |id| usize::try_from(id.0).map_or(0, |id| id / 8 + 1));
+        let padding = needed.saturating_sub(save.states.0.len());
+
+        self.write_len("states length", save.states.0.len() + padding)?;
+        self.out.write_bytes(&save.states.0)?;
+        self.out.write_bytes(&vec![0; padding])
+    }
+
+    fn write_header(&mut self, save: &SaveFile) -> Result<()> {
+        self.write_raw_string("Logic World save")?;
+
+        self.write_byte(save.format_version)?;
+        self.write_version(&save.game_version)?;
+        self.write_byte(save.save_type.to_byte())?;
+        self.write_len("component count", save.components.len())?;
+        self.write_len("wire count", save.wires.len())
+    }
+
+    // Mods keep the order they were parsed in, and any added since follow sorted by name, so the
+    // same save always produces the same bytes.
+    fn write_mod_versions(&mut self, save: &SaveFile) -> Result<()> {
+        self.write_len("mod version count", save.mod_versions.len())?;
+        let mut added: Vec<_> = save
+            .mod_versions
+            .iter()
+            .filter(|&(name, _)| !save.mod_order.contains(name))
+            .collect();
+        added.sort_unstable_by_key(|&(name, _)| name);
+        let parsed = save
+            .mod_order
+            .iter()
+            .filter_map(|name| save.mod_versions.get_key_value(name));
+        for (name, version) in parsed.chain(added) {
+            self.write_string(name, &save.raw_strings)?;
+            self.write_version(version)?;
+        }
+        Ok(())
+    }
+
+    fn write_comp_map(&mut self, save: &SaveFile) -> Result<()> {
+        self.write_len("component map size", save.comp_map.len())?;
+        for (num_id, text_id) in save.comp_map.in_order() {
+            self.write_id(num_id)?;
+            self.write_string(text_id, &save.raw_strings)?;
+        }
+        Ok(())
+    }
+
+    fn write_wire(&mut self, wire: &Wire) -> Result<()> {
+        self.write_peg_address(&wire.start)?;
+        self.write_peg_address(&wire.end)?;
+        self.write_state_id(wire.state_id)?;
+        self.write_float(wire.rotation)
+    }
+
+    fn write_peg_address(&mut self, address: &PegAddress) -> Result<()> {
+        match address.type_ {
+            PegType::Input => self.write_byte(1)?,
+            PegType::Output => self.write_byte(2)?,
+        }
+        self.write_address(address.component)?;
+        self.write_int(address.index)
+    }
+
+    fn write_component(&mut self, comp: &Component, ids: &HashMap<&str, u16>) -> Result<()> {
+        self.write_address(comp.address)?;
+        self.write_address(comp.parent)?;
+        let id = ids
+            .get(&*comp.id)
+            .ok_or_else(|| anyhow!("Missing {} in mapping", comp.id))?;
+        self.write_id(*id)?;
+
+        self.write_int(comp.position.x)?;
+        self.write_int(comp.position.y)?;
+        self.write_int(comp.position.z)?;
+
+        self.write_float(comp.rotation.x)?;
+        self.write_float(comp.rotation.y)?;
+        self.write_float(comp.rotation.z)?;
+        self.write_float(comp.rotation.w)?;
+
+        self.write_len("input count", comp.inputs.len())?;
+        for inp in &comp.inputs {
+            self.write_state_id(*inp)?;
+        }
+        self.write_len("output count", comp.outputs.len())?;
+        for inp in &comp.outputs {
+            self.write_state_id(*inp)?;
+        }
+
+        match self.do_customdata(&comp.custom_data)? {
+            Some(custom_data) => {
+                self.write_len("custom data length", custom_data.len())?;
+                self.out.write_bytes(&custom_data)?;
+            }
+            None => self.write_int(-1)?,
+        }
+
+        Ok(())
+    }
+
+    fn do_customdata(&mut self, data: &CustomData) -> Result<Option<Vec<u8>>> {
+        Ok(Some(match data {
+            CustomData::None => return Ok(None),
+            CustomData::Unknown(data) => data.clone(),
+            CustomData::Display { color_mode } => color_mode.to_le_bytes().to_vec(),
+            CustomData::Delayer { ticks } => ticks.to_le_bytes().to_vec(),
+            CustomData::Label {
+                text,
+                font_size,
+                color,
+            } => {
+                let mut data = Vec::with_capacity(text.len() + 11);
+                let len = checked_len("label length", text.len()).map_err(|err| anyhow!(err))?;
+                data.extend_from_slice(&len.to_le_bytes());
+                data.extend_from_slice(text.as_bytes());
+                data.extend_from_slice(&font_size.to_le_bytes());
+                data.extend_from_slice(&[color.r, color.g, color.b]);
+                data
+            }
+            CustomData::Switch { color, on } => {
+                vec![color.r, color.g, color.b, if *on { 1 } else { 0 }]
+            }
+        }))
+    }
+
+    fn write_version(&mut self, version: &Version) -> Result<()> {
+        self.write_int(version.0)?;
+        self.write_int(version.1)?;
+        self.write_int(version.2)?;
+        self.write_int(version.3)
+    }
+
+    fn write_string(
+        &mut self,
+        data: &str,
+        raw_strings: &HashMap<Box<str>, Box<[u8]>>,
+    ) -> Result<()> {
+        let bytes = raw_strings.get(data).map_or(data.as_bytes(), |raw| raw);
+        self.write_len("string length", bytes.len())?;
+        self.out.write_bytes(bytes)
+    }
+
+    fn write_byte(&mut self, data: u8) -> Result<()> {
+        self.out.write_bytes(&[data])
+    }
+
+    fn write_id(&mut self, data: u16) -> Result<()> {
+        self.out.write_bytes(&data.to_le_bytes())
+    }
+
+    fn write_float(&mut self, data: f32) -> Result<()> {
+        self.out.write_bytes(&data.to_le_bytes())
+    }
+
+    fn write_address(&mut self, Address(data): Address) -> Result<()> {
+        self.out.write_bytes(&data.to_le_bytes())
+    }
+
+    fn write_state_id(&mut self, StateId(data): StateId) -> Result<()> {
+        self.write_int(data)
+    }
+
+    fn write_len(&mut self, what: &'static str, len: usize) -> Result<()> {
+        let len = checked_len(what, len).map_err(|err| anyhow!(err))?;
+        self.write_int(len)
+    }
+
+    fn write_int(&mut self, data: i32) -> Result<()> {
+        self.out.write_bytes(&data.to_le_bytes())
+    }
+
+    fn write_raw_string(&mut self, data: &str) -> Result<()> {
+        self.out.write_bytes(data.as_bytes())
+    }
+}
+
+impl SaveFile {
+    /// Size of the written save in bytes, exact unless states need padding or strings were
+    /// parsed from invalid UTF-8.
+    pub fn serialized_size_estimate(&self) -> usize {
+        let header = 16 + 1 + 16 + 1 + 4 + 4;
+        let mod_versions: usize = self
+            .mod_versions
+            .keys()
+            .map(|name| 4 + name.len() + 16)
+            .sum();
+        let comp_map: usize = self
+            .comp_map
+            .iter()
+            .map(|(_, name)| 2 + 4 + name.len())
+            .sum();
+        let components: usize = self
+            .components
+            .iter()
+            .map(|comp| {
+                let custom_data = match &comp.custom_data {
+                    CustomData::None => 0,
+                    CustomData::Unknown(data) => data.len(),
+                    CustomData::Switch { .. }
+                    | CustomData::Display { .. }
+                    | CustomData::Delayer { .. } => 4,
+                    CustomData::Label { text, .. } => 4 + text.len() + 7,
+                };
+                50 + 4 * (comp.inputs.len() + comp.outputs.len()) + custom_data
+            })
+            .sum();
+        let wires = self.wires.len() * 26;
+        let states = 4 + self.states.0.len();
+        header + 4 + mod_versions + 4 + comp_map + components + wires + states + 16
+    }
+}
+
+/// Converts a length to the `i32` the format stores it as.
+fn checked_len(what: &'static str, len: usize) -> Result<i32, WriteError> {
+    i32::try_from(len).map_err(|_| WriteError::TooLarge { what, len })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_wire_writes_both_pegs() {
+        let wire = Wire {
+            start: PegAddress {
+                type_: PegType::Output,
+                component: Address(3),
+                index: 1,
+            },
+            end: PegAddress {
+                type_: PegType::Input,
+                component: Address(7),
+                index: 2,
+            },
+            state_id: StateId(5),
+            rotation: 0.5,
+        };
+        let mut writer = Writer::new();
+        writer.write_wire(&wire).unwrap();
+        let out = writer.into_inner();
+
+        assert_eq!(out.len(), 26);
+        assert_eq!(out[0], 2);
+        assert_eq!(out[1..5], 3u32.to_le_bytes());
+        assert_eq!(out[5..9], 1i32.to_le_bytes());
+        assert_eq!(out[9], 1);
+        assert_eq!(out[10..14], 7u32.to_le_bytes());
+        assert_eq!(out[14..18], 2i32.to_le_bytes());
+        assert_eq!(out[18..22], 5i32.to_le_bytes());
+        assert_eq!(out[22..26], 0.5f32.to_le_bytes());
+    }
+}
@@ -0,0 +1,261 @@
+//! Saves put together with the builders have to be consistent without any fixing up.
+
+mod common;
+
+use common::peg;
+use logic_world_save::{
+    parse_save_bytes,
+    Address,
+    CircuitBuilder,
+    Color,
+    CustomData,
+    PegAddress,
+    PegType,
+    Quat,
+    SaveFile,
+    Vec3,
+    Version,
+    Writer,
+};
+
+fn switch(on: bool) -> CustomData {
+    CustomData::Switch {
+        color: Color::from_rgb(10, 20, 30),
+        on,
+    }
+}
+
+fn output_on(save: &SaveFile, address: Address) -> bool {
+    let comp = save.find_component(address).unwrap();
+    save.get_state(comp.outputs[0]).unwrap()
+}
+
+#[test]
+fn component_builder_turns_on_switch_output() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let on = save
+        .build_component("MHG.Switch")
+        .outputs(1)
+        .switch_on(true)
+        .add()
+        .unwrap();
+    let off = save
+        .build_component("MHG.Switch")
+        .outputs(1)
+        .switch_on(false)
+        .add()
+        .unwrap();
+    assert!(output_on(&save, on));
+    assert!(!output_on(&save, off));
+    assert!(save.validate().is_empty());
+}
+
+#[test]
+fn add_component_turns_on_switch_output() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let address = save
+        .add_component(
+            "MHG.Switch",
+            Address::ROOT,
+            Vec3::zero(),
+            Quat::IDENTITY,
+            0,
+            1,
+            switch(true),
+        )
+        .unwrap();
+    assert!(output_on(&save, address));
+}
+
+#[test]
+fn circuit_builder_turns_on_switch_output() {
+    let mut builder = CircuitBuilder::new();
+    let on = builder
+        .add_component("MHG.Switch", Vec3::zero(), Quat::IDENTITY, switch(true))
+        .unwrap();
+    let off = builder
+        .add_component("MHG.Switch", Vec3::zero(), Quat::IDENTITY, switch(false))
+        .unwrap();
+    let save = builder.build(Version(0, 0, 0, 0));
+    assert!(output_on(&save, on));
+    assert!(!output_on(&save, off));
+}
+
+#[test]
+fn one_bit_full_adder() {
+    let mut builder = CircuitBuilder::new();
+    let place = |builder: &mut CircuitBuilder, type_id: &str, x: i32, custom_data| {
+        builder
+            .add_component(type_id, Vec3 { x, y: 0, z: 0 }, Quat::IDENTITY, custom_data)
+            .unwrap()
+    };
+    let a = place(&mut builder, "MHG.Switch", 0, switch(true));
+    let b = place(&mut builder, "MHG.Switch", 300, switch(false));
+    let carry_in = place(&mut builder, "MHG.Switch", 600, switch(true));
+    let half_sum = place(&mut builder, "MHG.XorGate", 900, CustomData::None);
+    let sum_gate = place(&mut builder, "MHG.XorGate", 1200, CustomData::None);
+    let half_carry = place(&mut builder, "MHG.AndGate", 1500, CustomData::None);
+    let carry_gate = place(&mut builder, "MHG.AndGate", 1800, CustomData::None);
+    let sum = place(&mut builder, "MHG.Peg", 2100, CustomData::None);
+    let carry_out = place(&mut builder, "MHG.Peg", 2400, CustomData::None);
+
+    let output = |component| PegAddress {
+        type_: PegType::Output,
+        component,
+        index: 0,
+    };
+    let input = |component, index| PegAddress {
+        type_: PegType::Input,
+        component,
+        index,
+    };
+    for (from, to) in [
+        (output(a), input(half_sum, 0)),
+        (output(b), input(half_sum, 1)),
+        (output(a), input(half_carry, 0)),
+        (output(b), input(half_carry, 1)),
+        (output(half_sum), input(sum_gate, 0)),
+        (output(carry_in), input(sum_gate, 1)),
+        (output(half_sum), input(carry_gate, 0)),
+        (output(carry_in), input(carry_gate, 1)),
+        (output(sum_gate), input(sum, 0)),
+        // Two outputs driving one input act as an OR.
+        (output(half_carry), input(carry_out, 0)),
+        (output(carry_gate), input(carry_out, 0)),
+    ] {
+        builder.connect(from, to).unwrap();
+    }
+
+    let save = builder.build(Version(0, 91, 3, 0));
+    assert_eq!(save.validate(), []);
+    assert_eq!(save.components.len(), 9);
+    assert_eq!(save.wires.len(), 11);
+    let bytes = Writer::new().write(&save).unwrap();
+    let parsed = parse_save_bytes(&bytes).unwrap();
+    assert_eq!(parsed, save);
+    assert_eq!(Writer::new().write(&parsed).unwrap(), bytes);
+}
+
+#[test]
+fn circuit_builder_needs_peg_counts_for_unknown_types() {
+    let mut builder = CircuitBuilder::new();
+    assert!(builder
+        .add_component("Mod.Thing", Vec3::zero(), Quat::IDENTITY, CustomData::None)
+        .is_err());
+    let address = builder.add_component_with_pegs(
+        "Mod.Thing",
+        Vec3::zero(),
+        Quat::IDENTITY,
+        CustomData::None,
+        2,
+        3,
+    );
+    let save = builder.build(Version(0, 0, 0, 0));
+    let comp = save.find_component(address).unwrap();
+    assert_eq!((comp.inputs.len(), comp.outputs.len()), (2, 3));
+}
+
+#[test]
+fn wires_keep_their_rotation() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let a = save
+        .build_component("MHG.Buffer")
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap();
+    let b = save
+        .build_component("MHG.Buffer")
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap();
+    assert!(save
+        .add_wire_with_rotation(peg(PegType::Input, a, 0), peg(PegType::Input, b, 0), 1.5)
+        .is_err());
+    save.add_wire_with_rotation(peg(PegType::Output, a, 0), peg(PegType::Input, b, 0), 1.5)
+        .unwrap();
+    save.add_wire_any_with_rotation(peg(PegType::Input, a, 0), peg(PegType::Input, b, 0), -0.5)
+        .unwrap();
+
+    let rotations: Vec<_> = save.wires.iter().map(|wire| wire.rotation).collect();
+    assert_eq!(rotations, [1.5, -0.5]);
+    assert!(save.validate().is_empty());
+}
+
+#[test]
+fn add_component_places_it_on_an_existing_parent() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let board = save
+        .add_component(
+            "MHG.CircuitBoard",
+            Address::ROOT,
+            Vec3::zero(),
+            Quat::IDENTITY,
+            0,
+            0,
+            CustomData::None,
+        )
+        .unwrap();
+    let gate = save
+        .add_component(
+            "MHG.Inverter",
+            board,
+            Vec3 { x: 0, y: 1, z: 0 },
+            Quat::IDENTITY,
+            1,
+            1,
+            CustomData::None,
+        )
+        .unwrap();
+    assert_eq!(save.find_component(gate).unwrap().parent, board);
+
+    let missing = Address(board.0 + 100);
+    let result = save.add_component(
+        "MHG.Inverter",
+        missing,
+        Vec3::zero(),
+        Quat::IDENTITY,
+        1,
+        1,
+        CustomData::None,
+    );
+    assert!(result.is_err());
+    assert_eq!(save.components.len(), 2);
+    assert!(save.validate().is_empty());
+}
+
+#[test]
+fn add_wire_checks_the_pegs_exist() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let button = save.build_component("MHG.Button").outputs(1).add().unwrap();
+    let gate = save
+        .build_component("MHG.Inverter")
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap();
+    let missing = Address(gate.0 + 100);
+
+    for (to, index) in [(missing, 0), (gate, 1), (gate, -1)] {
+        let result = save.add_wire(
+            (button.0, PegType::Output, 0),
+            (to.0, PegType::Input, index),
+        );
+        assert!(result.is_err(), "{to} {index}");
+    }
+    assert!(save
+        .add_wire((button.0, PegType::Output, 0), (gate.0, PegType::Output, 0))
+        .is_err());
+    assert!(save.wires.is_empty());
+
+    let state_id = save
+        .add_wire((button.0, PegType::Output, 0), (gate.0, PegType::Input, 0))
+        .unwrap();
+    let input = save.find_component(gate).unwrap().inputs[0];
+    assert_eq!(state_id, input);
+    assert_eq!(save.wires.len(), 1);
+    assert_eq!(save.wires[0].state_id, input);
+    assert_eq!(save.wires[0].rotation, 0.);
+    assert!(save.validate().is_empty());
+}
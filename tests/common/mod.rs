@@ -0,0 +1,21 @@
+//! Helpers shared by the integration tests, not every test file uses all of them.
+
+#![allow(dead_code)]
+
+use logic_world_save::{Address, PegAddress, PegType};
+
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path = format!(
+        "{}/tests/fixtures/{name}.logicworld",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    std::fs::read(&path).unwrap_or_else(|err| panic!("reading {path}: {err}"))
+}
+
+pub fn peg(type_: PegType, component: Address, index: i32) -> PegAddress {
+    PegAddress {
+        type_,
+        component,
+        index,
+    }
+}
@@ -0,0 +1,186 @@
+//! Renumbering state ids and addresses must not change what the save does.
+
+use logic_world_save::{
+    Address,
+    ChildPolicy,
+    Color,
+    Component,
+    CustomData,
+    PegType,
+    Quat,
+    SaveFile,
+    StateId,
+    Vec3,
+    Version,
+};
+
+fn switch(save: &mut SaveFile, on: bool) -> Address {
+    save.add_component(
+        "MHG.Switch",
+        Address::ROOT,
+        Vec3::zero(),
+        Quat::IDENTITY,
+        0,
+        1,
+        CustomData::Switch {
+            color: Color::from_rgb(0, 0, 0),
+            on,
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn switch_stays_on_after_removal_and_compaction() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let before = switch(&mut save, false);
+    let on = switch(&mut save, true);
+    save.remove_component(before, ChildPolicy::Remove).unwrap();
+    save.compact_state_ids();
+
+    assert!(save.is_switch_on(on).unwrap());
+    let output = save.find_component(on).unwrap().outputs[0];
+    assert_eq!(output, StateId(1));
+    assert!(save.get_state(output).unwrap());
+    assert!(save.validate().is_empty());
+}
+
+#[test]
+fn compact_state_ids_keeps_ids_of_zero_and_below() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let on = switch(&mut save, true);
+    let address = save.get_free_address();
+    save.push_component(Component {
+        address,
+        parent: Address::ROOT,
+        id: "Mod.Thing".into(),
+        position: Vec3::zero(),
+        rotation: Quat::IDENTITY,
+        inputs: vec![StateId(0), StateId(-3)],
+        outputs: Vec::new(),
+        custom_data: CustomData::None,
+    });
+    let mapping = save.compact_state_ids();
+
+    assert!(!mapping.contains_key(&StateId(0)));
+    assert!(!mapping.contains_key(&StateId(-3)));
+    assert_eq!(save.components[1].inputs, [StateId(0), StateId(-3)]);
+    assert_eq!(save.highest_state_id, StateId(1));
+    assert!(save.is_switch_on(on).unwrap());
+    assert!(save.get_state(StateId(1)).unwrap());
+}
+
+/// Whether each peg is on, keyed by component address, so it can be compared across renumbering.
+fn peg_states(save: &SaveFile) -> Vec<(Address, Vec<bool>)> {
+    save.components
+        .iter()
+        .map(|comp| {
+            let pegs = comp.inputs.iter().chain(&comp.outputs);
+            let on = pegs.map(|&id| save.get_state(id).unwrap()).collect();
+            (comp.address, on)
+        })
+        .collect()
+}
+
+#[test]
+fn compact_state_ids_keeps_every_state() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let mut addresses = Vec::new();
+    for i in 0..20 {
+        let address = save
+            .build_component("MHG.AndGate")
+            .inputs(2)
+            .outputs(1)
+            .add()
+            .unwrap();
+        let comp = save.find_component(address).unwrap().clone();
+        for (index, &id) in comp.inputs.iter().chain(&comp.outputs).enumerate() {
+            save.set_state(id, (i + index) % 3 == 0).unwrap();
+        }
+        addresses.push(address);
+    }
+    let removed: Vec<_> = addresses.iter().copied().step_by(3).collect();
+    save.remove_components(&removed, ChildPolicy::Remove)
+        .unwrap();
+    let before = peg_states(&save);
+
+    save.compact_state_ids();
+
+    assert_eq!(peg_states(&save), before);
+    assert!(save.validate().is_empty());
+    let highest = save.highest_state_id.0;
+    assert_eq!(highest, 13 * 3);
+    let mut ids = save
+        .components
+        .iter()
+        .flat_map(|comp| comp.inputs.iter().chain(&comp.outputs));
+    assert!(ids.all(|id| id.0 <= highest));
+    assert!(save.states.len_bits() <= (highest as usize / 8 + 1) * 8);
+}
+
+#[test]
+fn compact_addresses_only_changes_addresses() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let mut boards = Vec::new();
+    for _ in 0..4 {
+        boards.push(save.build_component("MHG.CircuitBoard").add().unwrap());
+    }
+    let mut gates = Vec::new();
+    for (i, &board) in boards.iter().enumerate() {
+        let gate = save
+            .build_component("MHG.Inverter")
+            .parent(board)
+            .at(i as i32, 0, 0)
+            .inputs(1)
+            .outputs(1)
+            .add()
+            .unwrap();
+        gates.push(gate);
+    }
+    for pair in gates.windows(2) {
+        save.add_wire(
+            (pair[0].0, PegType::Output, 0),
+            (pair[1].0, PegType::Input, 0),
+        )
+        .unwrap();
+    }
+    save.remove_components(&[boards[0], gates[1]], ChildPolicy::MoveToRoot)
+        .unwrap();
+    let mut before = save.clone();
+
+    let mapping = save.compact_addresses();
+
+    assert!(save.validate().is_empty());
+    assert_eq!(save.highest_address, Address(save.components.len() as u32));
+    let rebase = |address: &mut Address| {
+        if let Some(&new) = mapping.get(address) {
+            *address = new;
+        }
+    };
+    for comp in &mut before.components {
+        rebase(&mut comp.address);
+        rebase(&mut comp.parent);
+    }
+    for wire in &mut before.wires {
+        rebase(&mut wire.start.component);
+        rebase(&mut wire.end.component);
+    }
+    before.rebuild_index();
+    assert!(before.diff(&save).is_empty());
+}
+
+#[test]
+fn prune_comp_map_drops_entries_nothing_uses() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let used = switch(&mut save, true);
+    let switch_id = save.comp_map.get_name("MHG.Switch").unwrap();
+    save.comp_map.ensure("Mod.Unused");
+    assert!(save.comp_map.contains_name("Mod.Unused"));
+
+    assert_eq!(save.prune_comp_map(), 1);
+    assert!(!save.comp_map.contains_name("Mod.Unused"));
+    assert_eq!(save.comp_map.get_name("MHG.Switch").unwrap(), switch_id);
+    assert!(save.find_component(used).is_some());
+    assert!(save.validate().is_empty());
+    assert_eq!(save.prune_comp_map(), 0);
+}
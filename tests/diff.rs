@@ -0,0 +1,106 @@
+//! Diffing two saves and patching one into the other.
+
+mod common;
+
+use common::fixture;
+use logic_world_save::{parse_save_bytes, PegType, SaveFile, StateId, Vec3, Version};
+
+/// Three buffers wired in a row.
+fn chain() -> SaveFile {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let gates: Vec<_> = (0..3)
+        .map(|x| {
+            save.build_component("MHG.Buffer")
+                .at(x, 0, 0)
+                .inputs(1)
+                .outputs(1)
+                .add()
+                .unwrap()
+        })
+        .collect();
+    for pair in gates.windows(2) {
+        save.add_wire(
+            (pair[0].0, PegType::Output, 0),
+            (pair[1].0, PegType::Input, 0),
+        )
+        .unwrap();
+    }
+    save
+}
+
+#[test]
+fn diff_against_itself_is_empty() {
+    for name in ["wires", "custom_data", "ordering", "subassembly"] {
+        let save = parse_save_bytes(&fixture(name)).unwrap();
+        assert!(save.diff(&save).is_empty(), "{name}");
+    }
+    let save = chain();
+    assert!(save.diff(&save.clone()).is_empty());
+}
+
+#[test]
+fn rotating_a_wire_shows_up_in_the_diff() {
+    let base = chain();
+    let mut other = base.clone();
+    other.wires[1].rotation = 2.5;
+
+    let diff = base.diff(&other);
+    assert!(diff.removed_wires.is_empty());
+    assert!(diff.added_wires.is_empty());
+    assert_eq!(diff.rotated_wires.len(), 1);
+    assert_eq!(diff.rotated_wires[0].1, 2.5);
+
+    let mut patched = base.clone();
+    diff.apply(&mut patched).unwrap();
+    assert_eq!(patched, other);
+}
+
+#[test]
+fn applying_a_diff_reproduces_the_other_save() {
+    let base = chain();
+    let mut other = base.clone();
+    let removed = other.components[0].address;
+    other.components.remove(0);
+    other.wires.remove(0);
+    other.components[0].position = Vec3 { x: 5, y: 5, z: 5 };
+    other.rebuild_index();
+
+    let diff = base.diff(&other);
+    assert_eq!(diff.removed_components, [removed]);
+    assert_eq!(diff.removed_wires.len(), 1);
+    assert_eq!(diff.modified_components.len(), 1);
+
+    let mut patched = base.clone();
+    diff.apply(&mut patched).unwrap();
+    assert!(patched.diff(&other).is_empty());
+}
+
+#[test]
+fn changed_states_show_up_in_the_diff() {
+    let base = chain();
+    let mut other = base.clone();
+    let state_id = other.wires[0].state_id;
+    other.states.set(state_id, true);
+    other.states.set(StateId(40), true);
+
+    let diff = base.diff(&other);
+    assert_eq!(diff.changed_states, [(state_id, true), (StateId(40), true)]);
+
+    let mut patched = base.clone();
+    diff.apply(&mut patched).unwrap();
+    assert_eq!(patched, other);
+    assert!(other.diff(&base).changed_states.iter().all(|&(_, on)| !on));
+}
+
+#[test]
+fn removing_a_missing_component_fails() {
+    let base = chain();
+    let mut other = base.clone();
+    other.components.clear();
+    other.wires.clear();
+    let diff = base.diff(&other);
+
+    let mut patched = base.clone();
+    patched.components.pop();
+    assert!(diff.apply(&mut patched).is_err());
+}
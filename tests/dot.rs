@@ -0,0 +1,103 @@
+//! Rendering a save as a Graphviz graph.
+
+use logic_world_save::{Address, PegAddress, PegType, SaveFile, StateId, Version, Wire};
+
+/// A board holding a board holding an inverter, wired to an inverter next to the boards.
+fn nested_boards() -> (SaveFile, [Address; 4]) {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let outer = save.build_component("MHG.CircuitBoard").add().unwrap();
+    let inner = save
+        .build_component("MHG.CircuitBoard")
+        .parent(outer)
+        .add()
+        .unwrap();
+    let gate = |save: &mut SaveFile, parent| {
+        save.build_component("MHG.Inverter")
+            .parent(parent)
+            .inputs(1)
+            .outputs(1)
+            .add()
+            .unwrap()
+    };
+    let inside = gate(&mut save, inner);
+    let outside = gate(&mut save, Address::ROOT);
+    save.add_wire(
+        (inside.0, PegType::Output, 0),
+        (outside.0, PegType::Input, 0),
+    )
+    .unwrap();
+    (save, [outer, inner, inside, outside])
+}
+
+/// The lines between the opening of `cluster` and its closing brace.
+fn cluster_body<'a>(dot: &'a str, cluster: &str) -> Vec<&'a str> {
+    let mut lines = dot
+        .lines()
+        .skip_while(|line| !line.ends_with(&format!("{cluster} {{")));
+    let open = lines.next().expect("cluster is in the graph");
+    let indent = open.len() - open.trim_start().len();
+    lines
+        .take_while(|line| line.len() - line.trim_start().len() > indent || line.trim().is_empty())
+        .collect()
+}
+
+#[test]
+fn dot_output_is_well_formed() {
+    let (save, [_, _, inside, outside]) = nested_boards();
+    let dot = save.to_dot();
+    assert!(dot.starts_with("digraph circuit {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+    let state_id = save.wires[0].state_id;
+    assert!(dot.contains(&format!(
+        "    c{}:o0 -> c{}:i0 [label=\"{state_id}\"];",
+        inside.0, outside.0
+    )));
+    for comp in &save.components {
+        let node = format!("c{} [label=", comp.address.0);
+        assert_eq!(dot.matches(&node).count(), 1, "{node}");
+    }
+}
+
+#[test]
+fn each_board_is_drawn_inside_its_own_nested_cluster() {
+    let (save, [outer, inner, inside, outside]) = nested_boards();
+    let dot = save.to_dot();
+    let outer_body = cluster_body(&dot, &format!("cluster_{}", outer.0));
+    let inner_body = cluster_body(&dot, &format!("cluster_{}", inner.0));
+    let has_node = |body: &[&str], address: Address| {
+        body.iter()
+            .any(|line| line.trim_start().starts_with(&format!("c{} [", address.0)))
+    };
+
+    assert!(has_node(&outer_body, outer));
+    assert!(has_node(&outer_body, inner));
+    assert!(has_node(&outer_body, inside));
+    assert!(!has_node(&outer_body, outside));
+
+    assert!(has_node(&inner_body, inner));
+    assert!(has_node(&inner_body, inside));
+    assert!(!has_node(&inner_body, outer));
+}
+
+#[test]
+fn negative_peg_indices_point_at_the_node() {
+    let (mut save, [.., inside, outside]) = nested_boards();
+    save.wires.push(Wire {
+        start: PegAddress {
+            type_: PegType::Output,
+            component: inside,
+            index: -1,
+        },
+        end: PegAddress {
+            type_: PegType::Input,
+            component: outside,
+            index: 0,
+        },
+        state_id: StateId(1),
+        rotation: 0.,
+    });
+    let dot = save.to_dot();
+    assert!(!dot.contains(":o-1"));
+    assert!(dot.contains(&format!("    c{} -> c{}:i0", inside.0, outside.0)));
+}
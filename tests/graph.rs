@@ -0,0 +1,70 @@
+//! Following signals through the wires of a save.
+
+use logic_world_save::{Address, PegType, SaveFile, Version};
+
+fn gate(save: &mut SaveFile, x: i32) -> Address {
+    save.build_component("MHG.Inverter")
+        .at(x, 0, 0)
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap()
+}
+
+fn wire(save: &mut SaveFile, from: Address, to: Address) {
+    save.add_wire((from.0, PegType::Output, 0), (to.0, PegType::Input, 0))
+        .unwrap();
+}
+
+/// `a` drives `b` and `c`, `c`'s input is wired straight to `d`'s input and `e` is on its own.
+fn circuit() -> (SaveFile, [Address; 5]) {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let gates = [0, 1, 2, 3, 4].map(|x| gate(&mut save, x));
+    let [a, b, c, d, _] = gates;
+    wire(&mut save, a, b);
+    wire(&mut save, a, c);
+    save.add_wire_any((c.0, PegType::Input, 0), (d.0, PegType::Input, 0))
+        .unwrap();
+    (save, gates)
+}
+
+fn sorted(addresses: impl IntoIterator<Item = Address>) -> Vec<Address> {
+    let mut addresses: Vec<_> = addresses.into_iter().collect();
+    addresses.sort();
+    addresses
+}
+
+#[test]
+fn connected_components_group_wired_gates() {
+    let (save, [a, b, c, d, e]) = circuit();
+    let groups = save.wire_graph().connected_components();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(sorted(groups[0].iter().copied()), [a, b, c, d]);
+    assert_eq!(sorted(groups[1].iter().copied()), [e]);
+}
+
+#[test]
+fn signals_follow_outputs_and_input_to_input_wires() {
+    let (save, [a, b, c, d, e]) = circuit();
+    let graph = save.wire_graph();
+    assert_eq!(sorted(graph.reachable_from(a)), [b, c, d]);
+    assert!(graph.reachable_from(b).is_empty());
+    assert!(graph.reachable_from(e).is_empty());
+    assert_eq!(graph.fan_out(a, 0), 2);
+    assert_eq!(graph.fan_out(b, 0), 0);
+}
+
+#[test]
+fn feedback_loops_are_cycles() {
+    let (mut save, [a, _, _, d, e]) = circuit();
+    assert!(!save.wire_graph().has_cycle());
+
+    wire(&mut save, e, e);
+    let graph = save.wire_graph();
+    assert!(graph.has_cycle());
+    assert_eq!(sorted(graph.reachable_from(e)), [e]);
+
+    wire(&mut save, d, a);
+    let graph = save.wire_graph();
+    assert!(graph.reachable_from(a).contains(&a));
+}
@@ -0,0 +1,249 @@
+//! Finding components and taking them out of a save.
+
+use logic_world_save::{Address, ChildPolicy, PegType, SaveFile, StateId, Version};
+
+/// A board with two gates on it, wired to each other and to a gate next to the board.
+fn board_with_children() -> (SaveFile, Address, [Address; 3]) {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let board = save.build_component("MHG.CircuitBoard").add().unwrap();
+    let gate = |save: &mut SaveFile, parent| {
+        save.build_component("MHG.Inverter")
+            .parent(parent)
+            .inputs(1)
+            .outputs(1)
+            .add()
+            .unwrap()
+    };
+    let a = gate(&mut save, board);
+    let b = gate(&mut save, board);
+    let outside = gate(&mut save, Address::ROOT);
+    save.add_wire((a.0, PegType::Output, 0), (b.0, PegType::Input, 0))
+        .unwrap();
+    save.add_wire((b.0, PegType::Output, 0), (outside.0, PegType::Input, 0))
+        .unwrap();
+    (save, board, [a, b, outside])
+}
+
+#[test]
+fn removing_a_board_removes_its_children_and_their_wires() {
+    let (mut save, board, [a, b, outside]) = board_with_children();
+    let removed = save.remove_component(board, ChildPolicy::Remove).unwrap();
+
+    assert_eq!(removed.component.address, board);
+    let descendants: Vec<_> = removed
+        .descendants
+        .iter()
+        .map(|comp| comp.address)
+        .collect();
+    assert_eq!(descendants, [a, b]);
+    assert_eq!(removed.wires.len(), 2);
+    assert!(save.wires.is_empty());
+    assert!(save.validate().is_empty());
+    assert_eq!(save.components.len(), 1);
+    assert!(save.find_component(outside).is_some());
+    assert!(save.wires_for_component(outside).is_empty());
+}
+
+#[test]
+fn removing_a_board_can_keep_its_children() {
+    let (mut save, board, [a, b, outside]) = board_with_children();
+    let removed = save
+        .remove_component(board, ChildPolicy::MoveToRoot)
+        .unwrap();
+
+    assert!(removed.descendants.is_empty());
+    assert!(removed.wires.is_empty());
+    assert!(save.validate().is_empty());
+    assert!(save.children_of(board).is_empty());
+    let mut top_level: Vec<_> = save
+        .top_level_components()
+        .iter()
+        .map(|comp| comp.address)
+        .collect();
+    top_level.sort();
+    assert_eq!(top_level, [a, b, outside]);
+    assert_eq!(save.wires_for_component(b).len(), 2);
+}
+
+#[test]
+fn removing_components_one_by_one_keeps_the_index_usable() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let gates: Vec<_> = (0..200)
+        .map(|_| {
+            save.build_component("MHG.Buffer")
+                .inputs(1)
+                .outputs(1)
+                .add()
+                .unwrap()
+        })
+        .collect();
+    for pair in gates.windows(2) {
+        save.add_wire(
+            (pair[0].0, PegType::Output, 0),
+            (pair[1].0, PegType::Input, 0),
+        )
+        .unwrap();
+    }
+
+    for &address in gates.iter().step_by(2) {
+        save.remove_component(address, ChildPolicy::Remove).unwrap();
+        assert!(save.find_component(address).is_none());
+    }
+
+    assert_eq!(save.components.len(), 100);
+    assert!(save.wires.is_empty());
+    assert!(save.validate().is_empty());
+    for &address in gates.iter().skip(1).step_by(2) {
+        assert_eq!(save.find_component(address).unwrap().address, address);
+    }
+    // The freed state ids are handed out again.
+    assert!(save.get_free_state_id().0 < 400);
+}
+
+#[test]
+fn removing_a_component_keeps_state_ids_used_by_unwired_components() {
+    let (mut save, _, [a, b, outside]) = board_with_children();
+    let shared = save.find_component(a).unwrap().inputs[0];
+    save.find_component_mut(outside).unwrap().outputs[0] = shared;
+
+    save.remove_component(a, ChildPolicy::Remove).unwrap();
+    save.remove_component(b, ChildPolicy::Remove).unwrap();
+    let handed_out: Vec<_> = (0..4).map(|_| save.get_free_state_id()).collect();
+    assert!(!handed_out.contains(&shared), "{handed_out:?}");
+}
+
+#[test]
+fn find_component_after_push_and_after_rebuild() {
+    let (mut save, board, [a, b, outside]) = board_with_children();
+    let mut pushed = save.find_component(outside).unwrap().clone();
+    pushed.address = Address(100);
+    save.push_component(pushed.clone());
+    assert_eq!(save.find_component(Address(100)), Some(&pushed));
+
+    // Pushed past the index and reordered under it, the lookups still find the right one.
+    let mut direct = pushed.clone();
+    direct.address = Address(101);
+    save.components.push(direct.clone());
+    save.components.reverse();
+    for address in [board, a, b, outside, Address(100), Address(101)] {
+        assert_eq!(save.find_component(address).unwrap().address, address);
+    }
+
+    save.rebuild_index();
+    for address in [board, a, b, outside, Address(100), Address(101)] {
+        assert_eq!(save.find_component(address).unwrap().address, address);
+        assert_eq!(save.find_component_mut(address).unwrap().address, address);
+    }
+    assert_eq!(save.find_component(Address(101)), Some(&direct));
+    assert_eq!(save.find_component(Address(102)), None);
+}
+
+#[test]
+fn children_of_only_gives_direct_children() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let place = |save: &mut SaveFile, parent| {
+        save.build_component("MHG.CircuitBoard")
+            .parent(parent)
+            .add()
+            .unwrap()
+    };
+    let world = place(&mut save, Address::ROOT);
+    let other = place(&mut save, Address::ROOT);
+    let middle = place(&mut save, world);
+    let beside = place(&mut save, world);
+    let deepest = place(&mut save, middle);
+
+    let children = |save: &SaveFile, parent| -> Vec<Address> {
+        save.children_of(parent)
+            .iter()
+            .map(|comp| comp.address)
+            .collect()
+    };
+    assert_eq!(children(&save, world), [middle, beside]);
+    assert_eq!(children(&save, middle), [deepest]);
+    assert!(children(&save, deepest).is_empty());
+    assert!(children(&save, other).is_empty());
+    let top: Vec<_> = save
+        .top_level_components()
+        .iter()
+        .map(|comp| comp.address)
+        .collect();
+    assert_eq!(top, [world, other]);
+
+    save.components.reverse();
+    save.rebuild_index();
+    assert_eq!(children(&save, world), [beside, middle]);
+    assert_eq!(children(&save, middle), [deepest]);
+}
+
+#[test]
+fn extract_subcircuit_drops_wires_leaving_the_selection() {
+    let (save, board, [a, b, outside]) = board_with_children();
+    let extracted = save.extract_subcircuit(&[board, a, b]).unwrap();
+
+    assert_eq!(extracted.components.len(), 3);
+    assert_eq!(extracted.wires.len(), 1);
+    assert!(extracted.validate().is_empty());
+    let ids: Vec<&str> = extracted.components.iter().map(|comp| &*comp.id).collect();
+    assert_eq!(ids, ["MHG.CircuitBoard", "MHG.Inverter", "MHG.Inverter"]);
+    let [new_board, new_a, new_b] = [0, 1, 2].map(|i| extracted.components[i].address);
+    assert_eq!(extracted.children_of(new_board).len(), 2);
+    let wire = &extracted.wires[0];
+    assert_eq!((wire.start.component, wire.end.component), (new_a, new_b));
+    assert_eq!(
+        wire.state_id,
+        extracted.find_component(new_b).unwrap().inputs[0]
+    );
+
+    // Seen from the gate outside, the wire crossing the boundary is dropped as well.
+    let extracted = save.extract_subcircuit(&[outside]).unwrap();
+    assert!(extracted.wires.is_empty());
+    assert!(extracted.validate().is_empty());
+}
+
+#[test]
+fn extract_subcircuit_keeps_state_ids_of_0_and_below() {
+    let (mut save, board, [a, b, _]) = board_with_children();
+    save.find_component_mut(a).unwrap().inputs[0] = StateId(0);
+    save.find_component_mut(b).unwrap().outputs[0] = StateId(-1);
+    let extracted = save.extract_subcircuit(&[board, a, b]).unwrap();
+
+    assert_eq!(extracted.components[1].inputs[0], StateId(0));
+    assert_eq!(extracted.components[2].outputs[0], StateId(-1));
+    assert_eq!(extracted.highest_state_id, StateId(2));
+}
+
+#[test]
+fn extract_subcircuit_fails_when_moving_to_the_origin_overflows() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let low = save
+        .build_component("MHG.Peg")
+        .at(i32::MIN, 0, 0)
+        .add()
+        .unwrap();
+    let high = save
+        .build_component("MHG.Peg")
+        .at(i32::MAX, 0, 0)
+        .add()
+        .unwrap();
+    assert!(save.extract_subcircuit(&[low, high]).is_err());
+    assert!(save.extract_subcircuit(&[high]).is_ok());
+}
+
+#[test]
+fn removing_a_board_removes_children_moved_onto_it() {
+    let (mut save, board, [a, b, outside]) = board_with_children();
+    save.set_parent(outside, a).unwrap();
+    assert!(save.set_parent(board, outside).is_err());
+    assert!(save.set_parent(outside, Address(99)).is_err());
+
+    let removed = save.remove_component(board, ChildPolicy::Remove).unwrap();
+    let mut descendants: Vec<_> = removed
+        .descendants
+        .iter()
+        .map(|comp| comp.address)
+        .collect();
+    descendants.sort();
+    assert_eq!(descendants, [a, b, outside]);
+    assert!(save.components.is_empty());
+}
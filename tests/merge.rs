@@ -0,0 +1,128 @@
+//! Combining two saves into one.
+
+use logic_world_save::{
+    Address,
+    Color,
+    Component,
+    CustomData,
+    PegType,
+    Quat,
+    SaveFile,
+    StateId,
+    Vec3,
+    Version,
+};
+
+/// A switch on a board, wired to an inverter next to it.
+fn circuit(on: bool) -> SaveFile {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let board = save.build_component("MHG.CircuitBoard").add().unwrap();
+    let switch = save
+        .build_component("MHG.Switch")
+        .parent(board)
+        .at(1, 0, 0)
+        .outputs(1)
+        .custom_data(CustomData::Switch {
+            color: Color::from_rgb(0, 0, 0),
+            on,
+        })
+        .add()
+        .unwrap();
+    let inverter = save
+        .build_component("MHG.Inverter")
+        .at(2, 0, 0)
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap();
+    save.add_wire(
+        (switch.0, PegType::Output, 0),
+        (inverter.0, PegType::Input, 0),
+    )
+    .unwrap();
+    save
+}
+
+#[test]
+fn merged_save_is_valid_and_moved_by_the_offset() {
+    let base = circuit(false);
+    let other = circuit(true);
+    let base_highest = base.highest_state_id;
+    let offset = Vec3 {
+        x: 100,
+        y: 0,
+        z: -5,
+    };
+
+    let merged = base.clone().merge(other.clone(), Some(offset)).unwrap();
+
+    assert_eq!(merged.validate(), []);
+    assert_eq!(merged.components.len(), 6);
+    assert_eq!(merged.wires.len(), 2);
+    for (old, new) in other.components.iter().zip(&merged.components[3..]) {
+        assert_eq!(new.position, old.position + offset);
+        assert_eq!(new.id, old.id);
+        let rebased: Vec<_> = old
+            .inputs
+            .iter()
+            .chain(&old.outputs)
+            .map(|id| StateId(id.0 + base_highest.0))
+            .collect();
+        let ids: Vec<_> = new.inputs.iter().chain(&new.outputs).copied().collect();
+        assert_eq!(ids, rebased);
+    }
+    assert_eq!(
+        merged.highest_state_id,
+        StateId(base_highest.0 + other.highest_state_id.0)
+    );
+    let switches: Vec<_> = merged
+        .find_components_by_type("MHG.Switch")
+        .iter()
+        .map(|comp| merged.is_switch_on(comp.address).unwrap())
+        .collect();
+    assert_eq!(switches, [false, true]);
+    let on_output = merged.components[4].outputs[0];
+    assert!(merged.get_state(on_output).unwrap());
+}
+
+#[test]
+fn merge_keeps_state_ids_of_zero_and_below() {
+    let base = circuit(true);
+    let mut other = SaveFile::empty(Version(0, 0, 0, 0));
+    other.comp_map.ensure("Mod.Thing");
+    other.push_component(Component {
+        address: Address(2),
+        parent: Address::ROOT,
+        id: "Mod.Thing".into(),
+        position: Vec3::zero(),
+        rotation: Quat::IDENTITY,
+        inputs: vec![StateId(0), StateId(-1)],
+        outputs: Vec::new(),
+        custom_data: CustomData::None,
+    });
+
+    let merged = base.clone().merge(other, None).unwrap();
+
+    assert_eq!(merged.components[3].inputs, [StateId(0), StateId(-1)]);
+    assert_eq!(merged.highest_state_id, base.highest_state_id);
+    assert_eq!(merged.states, base.states);
+}
+
+#[test]
+fn merge_fails_when_rebasing_a_state_id_overflows() {
+    let base = circuit(true);
+    let mut other = SaveFile::empty(Version(0, 0, 0, 0));
+    other.comp_map.ensure("Mod.Thing");
+    other.push_component(Component {
+        address: Address(2),
+        parent: Address::ROOT,
+        id: "Mod.Thing".into(),
+        position: Vec3::zero(),
+        rotation: Quat::IDENTITY,
+        inputs: vec![StateId(i32::MAX)],
+        outputs: Vec::new(),
+        custom_data: CustomData::None,
+    });
+
+    assert!(base.merge(other, None).is_err());
+}
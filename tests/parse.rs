@@ -0,0 +1,639 @@
+//! Parsing files that are unusual but valid, or broken in ways the parser has to survive.
+
+mod common;
+
+use common::fixture;
+use logic_world_save::{
+    parse_save_bytes,
+    CustomData,
+    Hexdump,
+    ParseError,
+    ParseOptions,
+    Parser,
+    Recovery,
+    SaveFile,
+    SaveHeader,
+    Section,
+    SliceSource,
+    StateId,
+    ValidationError,
+    Version,
+    Writer,
+    FORMAT_VERSION,
+};
+
+/// A save whose mod names are `names`, which may be any bytes.
+fn save_with_mods(names: &[&[u8]]) -> Vec<u8> {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    // Same length ASCII stand-ins, swapped for the real bytes after writing.
+    let placeholders: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| char::from(b'Q' + i as u8).to_string().repeat(name.len()))
+        .collect();
+    for placeholder in &placeholders {
+        save.mod_versions
+            .insert(placeholder.as_str().into(), Version(1, 0, 0, 0));
+    }
+    let mut bytes = Writer::new().write(&save).unwrap();
+    for (placeholder, name) in placeholders.iter().zip(names) {
+        let at = bytes
+            .windows(name.len())
+            .position(|window| window == placeholder.as_bytes())
+            .unwrap();
+        bytes[at..at + name.len()].copy_from_slice(name);
+    }
+    bytes
+}
+
+#[test]
+fn non_utf8_strings_keep_distinct_names() {
+    let bytes = fixture("non_utf8");
+    let save = parse_save_bytes(&bytes).unwrap();
+    assert_eq!(save.mod_versions.len(), 3);
+    let names: Vec<_> = save.comp_map.iter().map(|(_, name)| name).collect();
+    assert_eq!(names.len(), 3);
+    assert_ne!(names[0], names[1]);
+    assert_eq!(Writer::new().write(&save).unwrap(), bytes);
+}
+
+#[test]
+fn escaped_names_that_collide_are_made_unique() {
+    // U+10FFFF followed by a bad byte, and two bad bytes, would both become the same text.
+    let bytes = save_with_mods(&[b"\xF4\x8F\xBF\xBF\xFF", b"\xFF\xFF"]);
+    let save = parse_save_bytes(&bytes).unwrap();
+    assert_eq!(save.mod_versions.len(), 2);
+    assert_eq!(Writer::new().write(&save).unwrap(), bytes);
+}
+
+#[test]
+fn merging_different_bytes_with_the_same_name_fails() {
+    let base = parse_save_bytes(&save_with_mods(&[b"\xFF\xFF"])).unwrap();
+    let other = parse_save_bytes(&save_with_mods(&[b"\xF4\x8F\xBF\xBF\xFF"])).unwrap();
+    assert!(base.merge(other, None).is_err());
+}
+
+/// The start of a save up to and including the component count.
+fn header(components: i32) -> Vec<u8> {
+    let mut bytes = b"Logic World save".to_vec();
+    bytes.push(7);
+    for part in [0i32, 91, 3, 0] {
+        bytes.extend(part.to_le_bytes());
+    }
+    bytes.push(1);
+    bytes.extend(components.to_le_bytes());
+    bytes
+}
+
+/// Parses without telling the parser how long the input is, like reading from a file stream.
+fn parse_streamed(bytes: Vec<u8>) -> Result<SaveFile, ParseError> {
+    Parser::new(SliceSource(&bytes)).parse_save()
+}
+
+#[test]
+fn short_file_claiming_a_billion_components_fails() {
+    let mut bytes = header(1_000_000_000);
+    // No wires, mods or component map entries.
+    bytes.extend([0; 12]);
+    bytes.resize(100, 0);
+    assert!(parse_save_bytes(&bytes).is_err());
+    assert!(parse_streamed(bytes).is_err());
+}
+
+#[test]
+fn short_file_claiming_a_billion_byte_string_fails() {
+    let mut bytes = header(0);
+    bytes.extend(0i32.to_le_bytes());
+    // One mod with a name a billion bytes long.
+    bytes.extend(1i32.to_le_bytes());
+    bytes.extend(1_000_000_000i32.to_le_bytes());
+    bytes.resize(100, 0);
+    assert!(parse_save_bytes(&bytes).is_err());
+    assert!(parse_streamed(bytes).is_err());
+}
+
+#[test]
+fn short_file_claiming_a_billion_bytes_of_custom_data_fails() {
+    let mut bytes = header(1);
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend(0i32.to_le_bytes());
+    // Component map with MHG.Peg as id 1.
+    bytes.extend(1i32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(7i32.to_le_bytes());
+    bytes.extend(b"MHG.Peg");
+    // Address, parent, id, position, rotation and no pegs.
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend([0; 28]);
+    bytes.extend([0; 8]);
+    bytes.extend(1_000_000_000i32.to_le_bytes());
+    bytes.resize(bytes.len() + 20, 0);
+    assert!(parse_save_bytes(&bytes).is_err());
+    assert!(parse_streamed(bytes).is_err());
+}
+
+/// A save with one of everything, where the count named `negative` is written as -2 instead.
+fn save_with_negative(negative: &str) -> Vec<u8> {
+    let count = |field: &str, count: i32| if field == negative { -2 } else { count };
+    let mut bytes = header(count("component count", 1));
+    bytes.extend(count("wire count", 1).to_le_bytes());
+    bytes.extend(count("mod version count", 1).to_le_bytes());
+    bytes.extend(count("string length", 3).to_le_bytes());
+    bytes.extend(b"MHG");
+    bytes.extend([0; 16]);
+    bytes.extend(count("component map size", 1).to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(10i32.to_le_bytes());
+    bytes.extend(b"MHG.Switch");
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend([0; 28]);
+    bytes.extend(count("input count", 1).to_le_bytes());
+    bytes.extend(1i32.to_le_bytes());
+    bytes.extend(count("output count", 1).to_le_bytes());
+    bytes.extend(2i32.to_le_bytes());
+    bytes.extend(count("custom data length", 4).to_le_bytes());
+    bytes.extend([1, 2, 3, 1]);
+    for (type_, index) in [(2u8, 0i32), (1, 0)] {
+        bytes.push(type_);
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(index.to_le_bytes());
+    }
+    bytes.extend(1i32.to_le_bytes());
+    bytes.extend(0f32.to_le_bytes());
+    bytes.extend(count("state count", 1).to_le_bytes());
+    bytes.push(0b100);
+    bytes.extend(b"redstone sux lol");
+    bytes
+}
+
+#[test]
+fn negative_counts_fail_in_every_section() {
+    assert!(parse_save_bytes(&save_with_negative("")).is_ok());
+    for field in [
+        "component count",
+        "wire count",
+        "mod version count",
+        "string length",
+        "component map size",
+        "input count",
+        "output count",
+        "custom data length",
+        "state count",
+    ] {
+        let bytes = save_with_negative(field);
+        let err = parse_save_bytes(&bytes).unwrap_err();
+        let ParseError::At { source, .. } = &err else {
+            panic!("error without a location: {err:?}");
+        };
+        assert!(
+            matches!(**source, ParseError::NegativeCount { field: found, count: -2 } if found == field),
+            "{field}: {source:?}"
+        );
+        assert!(parse_streamed(bytes).is_err());
+    }
+}
+
+#[test]
+fn saves_before_format_version_7_are_reported_as_legacy() {
+    for version in [0, 4, 5, 6] {
+        let mut bytes = header(0);
+        bytes[16] = version;
+        bytes.resize(100, 0);
+        let err = parse_save_bytes(&bytes).unwrap_err();
+        let ParseError::At { source, .. } = err else {
+            panic!("error without a location: {err:?}");
+        };
+        assert!(
+            matches!(*source, ParseError::LegacyFormatVersion(found) if found == version),
+            "{source:?}"
+        );
+    }
+}
+
+#[test]
+fn located_errors_show_the_underlying_error() {
+    let mut bytes = header(0);
+    bytes[16] = 4;
+    let message = parse_save_bytes(&bytes).unwrap_err().to_string();
+    let (location, rest) = message.split_once(": ").unwrap();
+    assert!(
+        location.starts_with("Failed parsing header at byte "),
+        "{message}"
+    );
+    assert!(
+        rest.starts_with("Legacy save format version 4, re-save it in the game to upgrade it\n"),
+        "{message}"
+    );
+}
+
+#[test]
+fn tolerated_problems_are_kept_as_warnings() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let address = save.build_component("MHG.CircuitBoard").add().unwrap();
+    let copy = save.find_component(address).unwrap().clone();
+    save.push_component(copy);
+    let mut bytes = Writer::new().without_validation().write(&save).unwrap();
+    bytes[16] = FORMAT_VERSION + 1;
+
+    assert!(parse_save_bytes(&bytes).is_err());
+    let mut parser = Parser::from_bytes(&bytes).with_options(ParseOptions {
+        allow_unknown_version: true,
+        renumber_duplicate_addresses: true,
+        ..ParseOptions::default()
+    });
+    let parsed = parser.parse_save().unwrap();
+    assert_eq!(parsed.format_version, FORMAT_VERSION + 1);
+    assert_ne!(parsed.components[0].address, parsed.components[1].address);
+
+    let warnings: Vec<_> = parser
+        .warnings()
+        .iter()
+        .map(|warning| match warning {
+            ParseError::At { source, .. } => &**source,
+            other => other,
+        })
+        .collect();
+    assert!(
+        matches!(
+            warnings[..],
+            [
+                ParseError::UnsupportedFormatVersion(8),
+                ParseError::DuplicateAddress { first: 0, .. },
+            ]
+        ),
+        "{warnings:?}"
+    );
+}
+
+#[test]
+fn short_states_buffer_is_kept_and_padded_when_written() {
+    let mut bytes = header(1);
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend(1i32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(7i32.to_le_bytes());
+    bytes.extend(b"MHG.Peg");
+    // A peg on state id 20, while the states buffer only covers ids up to 7.
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend([0; 28]);
+    bytes.extend(1i32.to_le_bytes());
+    bytes.extend(20i32.to_le_bytes());
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend((-1i32).to_le_bytes());
+    bytes.extend(1i32.to_le_bytes());
+    bytes.push(0xff);
+    bytes.extend(b"redstone sux lol");
+
+    let mut save = parse_save_bytes(&bytes).unwrap();
+    assert_eq!(save.highest_state_id, StateId(20));
+    assert_eq!(save.states.0, [0xff]);
+    assert!(matches!(
+        save.validate()[..],
+        [ValidationError::ComponentStateOutOfRange {
+            state_id: StateId(20),
+            ..
+        }]
+    ));
+
+    let written = Writer::new().write(&save).unwrap();
+    let reparsed = parse_save_bytes(&written).unwrap();
+    assert_eq!(reparsed.states.0, [0xff, 0, 0]);
+    assert_eq!(reparsed.highest_state_id, StateId(20));
+    assert!(reparsed.validate().is_empty());
+    assert_eq!(Writer::new().write(&reparsed).unwrap(), written);
+
+    assert_eq!(save.get_free_state_id(), StateId(21));
+    assert_eq!(save.states.0, [0xff, 0, 0]);
+}
+
+#[test]
+fn truncated_save_reports_where_it_was_cut() {
+    let bytes = fixture("wires");
+    for (cut, section) in [
+        (120, Section::CompMap),
+        (200, Section::Component(1)),
+        (300, Section::Component(2)),
+        (501, Section::States),
+    ] {
+        let err = parse_streamed(bytes[..cut].to_vec()).unwrap_err();
+        let ParseError::At {
+            section: found,
+            offset,
+            ref source,
+            ..
+        } = err
+        else {
+            panic!("error without a location: {err:?}");
+        };
+        assert_eq!((found, offset), (section, cut as u64));
+        assert!(matches!(**source, ParseError::UnexpectedEof));
+        assert!(err
+            .to_string()
+            .starts_with(&format!("Failed parsing {section} at byte {cut:#X}:")));
+    }
+}
+
+#[test]
+fn hexdump_puts_the_caret_under_the_failing_byte() {
+    let dump = Hexdump {
+        start: 0x10,
+        bytes: (0..20).collect(),
+        caret: 0x22,
+    };
+    assert_eq!(
+        dump.to_string(),
+        "\n00000010  00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\
+         \n00000020  10 11 12 13\
+         \n                ^^"
+    );
+
+    // At the very end the caret goes after the last byte.
+    let dump = Hexdump {
+        start: 0,
+        bytes: vec![0xAA, 0xBB],
+        caret: 2,
+    };
+    assert_eq!(dump.to_string(), "\n00000000  AA BB\n                ^^");
+}
+
+#[test]
+fn hexdump_stops_at_the_ends_of_the_file() {
+    let bytes = fixture("wires");
+    let ParseError::At { context, .. } = parse_streamed(bytes[..20].to_vec()).unwrap_err() else {
+        panic!("error without a location");
+    };
+    assert_eq!(context.start, 0);
+    assert_eq!(context.bytes, &bytes[..20]);
+    assert_eq!(context.caret, 17);
+
+    let mut bad_peg = bytes.clone();
+    let last_wire = bytes.len() - 16 - 5 - 26;
+    // Peg type of the last wire.
+    bad_peg[last_wire] = 0x37;
+    let ParseError::At { context, .. } = parse_save_bytes(&bad_peg).unwrap_err() else {
+        panic!("error without a location");
+    };
+    assert_eq!(context.caret, last_wire as u64);
+    assert_eq!(context.start, last_wire as u64 + 1 - 32);
+    assert_eq!(
+        context.bytes,
+        &bad_peg[last_wire + 1 - 32..last_wire + 1 + 32]
+    );
+}
+
+#[test]
+fn files_that_are_not_saves_are_recognised() {
+    for (start, guess) in [
+        (
+            &b"\x1F\x8B\x08\x00\x00\x00\x00\x00\x00\x03\x01\x02\x03\x04\x05\x06"[..],
+            Some("gzip compressed data"),
+        ),
+        (
+            b"PK\x03\x04\x14\x00\x00\x00\x08\x00\x01\x02\x03\x04\x05\x06",
+            Some("a zip archive"),
+        ),
+        (
+            b"Seed: 1234\nName: w",
+            Some("a text file such as worldinfo.succ"),
+        ),
+        (
+            b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0A\x0B\x0C\x0D\x0E\x0F",
+            None,
+        ),
+    ] {
+        let err = parse_save_bytes(start).unwrap_err();
+        let ParseError::At { source, .. } = &err else {
+            panic!("error without a location: {err:?}");
+        };
+        assert!(
+            matches!(**source, ParseError::InvalidHeader { .. }),
+            "{source:?}"
+        );
+        let message = source.to_string();
+        match guess {
+            Some(guess) => assert!(
+                message.ends_with(&format!(", looks like {guess}")),
+                "{message}"
+            ),
+            None => assert!(!message.contains("looks like"), "{message}"),
+        }
+    }
+
+    let Err(ParseError::At { source, .. }) = parse_save_bytes(b"") else {
+        panic!("empty input parsed");
+    };
+    assert!(matches!(*source, ParseError::EmptyFile));
+}
+
+#[test]
+fn save_cut_inside_the_footer_says_how_much_is_missing() {
+    let bytes = fixture("wires");
+    let err = parse_save_bytes(&bytes[..bytes.len() - 5]).unwrap_err();
+    let ParseError::At { source, .. } = &err else {
+        panic!("error without a location: {err:?}");
+    };
+    assert!(matches!(**source, ParseError::MissingFooter { missing: 5 }));
+    assert_eq!(
+        source.to_string(),
+        "File ended 5 bytes early, footer missing"
+    );
+}
+
+#[test]
+fn lenient_parse_keeps_every_intact_component() {
+    let bytes = fixture("wires");
+    let save = parse_save_bytes(&bytes).unwrap();
+    for cut in 0..bytes.len() {
+        let (partial, errors) = Parser::new(SliceSource(&bytes[..cut])).parse_save_lenient();
+        let [ParseError::At { section, .. }] = errors.as_slice() else {
+            panic!("cut at {cut}: {errors:?}");
+        };
+        let intact = match *section {
+            Section::Component(index) => index,
+            Section::Wire(_) | Section::States | Section::Footer => save.components.len(),
+            _ => 0,
+        };
+        assert_eq!(
+            partial.components,
+            save.components[..intact],
+            "cut at {cut}"
+        );
+        assert_eq!(
+            partial.components_complete(),
+            intact == save.components.len(),
+            "cut at {cut}"
+        );
+        let wires = match *section {
+            Section::Wire(index) => index,
+            Section::States | Section::Footer => save.wires.len(),
+            _ => 0,
+        };
+        assert_eq!(partial.wires, save.wires[..wires], "cut at {cut}");
+        assert_eq!(partial.states.is_some(), *section == Section::Footer);
+        assert!(!partial.footer);
+    }
+}
+
+#[test]
+fn recovery_finds_the_states_length_from_the_footer() {
+    let bytes = fixture("wires");
+    let save = parse_save_bytes(&bytes).unwrap();
+    let (recovered, recoveries) = Parser::from_bytes(&bytes).parse_with_recovery().unwrap();
+    assert_eq!(recovered, save);
+    assert!(recoveries.is_empty());
+
+    let count_at = bytes.len() - 16 - 1 - 4;
+    for (count, claimed) in [(0, Some(0)), (3, Some(3)), (-2, None)] {
+        let mut bytes = bytes.clone();
+        bytes[count_at..count_at + 4].copy_from_slice(&i32::to_le_bytes(count));
+        assert!(parse_save_bytes(&bytes).is_err(), "{count}");
+
+        let (recovered, recoveries) = Parser::from_bytes(&bytes).parse_with_recovery().unwrap();
+        assert_eq!(recovered, save);
+        assert_eq!(recoveries, [Recovery::StatesLength { claimed, found: 1 }]);
+    }
+
+    let mut bytes = bytes;
+    bytes.extend(b"redstone sux lol");
+    let (recovered, recoveries) = Parser::from_bytes(&bytes).parse_with_recovery().unwrap();
+    assert_eq!(recovered, save);
+    assert_eq!(recoveries, [Recovery::TrailingBytes(16)]);
+}
+
+#[test]
+fn trailing_bytes_are_only_rejected_when_asked() {
+    let mut bytes = fixture("wires");
+    let save = parse_save_bytes(&bytes).unwrap();
+    let strict = ParseOptions {
+        reject_trailing_bytes: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        Parser::from_bytes(&bytes)
+            .with_options(strict.clone())
+            .parse_save()
+            .unwrap(),
+        save
+    );
+
+    bytes.extend([0xDE, 0xAD, 0xBE]);
+    assert_eq!(parse_save_bytes(&bytes).unwrap(), save);
+    let err = Parser::from_bytes(&bytes)
+        .with_options(strict)
+        .parse_save()
+        .unwrap_err();
+    let ParseError::At {
+        section, source, ..
+    } = &err
+    else {
+        panic!("error without a location: {err:?}");
+    };
+    assert_eq!(*section, Section::Footer);
+    assert!(matches!(**source, ParseError::TrailingBytes(3)));
+}
+
+#[test]
+fn custom_data_of_the_wrong_length_is_kept_as_unknown() {
+    let label = [
+        &5i32.to_le_bytes()[..],
+        b"hello",
+        &24u32.to_le_bytes(),
+        &[1, 2, 3],
+    ]
+    .concat();
+    for (id, valid) in [
+        ("MHG.Switch", vec![1, 2, 3, 1]),
+        ("MHG.Button", vec![1, 2, 3, 0]),
+        ("MHG.StandingDisplay", vec![12, 0, 0, 0]),
+        ("MHG.Delayer", vec![40, 0, 0, 0]),
+        ("MHG.Label", label),
+    ] {
+        let truncated = valid[..valid.len() - 1].to_vec();
+        let over_long = [&valid[..], &[0xEE]].concat();
+        for blob in [Vec::new(), truncated, over_long] {
+            let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+            save.build_component(id)
+                .custom_data(CustomData::Unknown(blob.clone()))
+                .add()
+                .unwrap();
+            let bytes = Writer::new().write(&save).unwrap();
+            let parsed = parse_save_bytes(&bytes).unwrap();
+            assert_eq!(
+                parsed.components[0].custom_data,
+                CustomData::Unknown(blob.clone()),
+                "{id} {blob:?}"
+            );
+            assert_eq!(Writer::new().write(&parsed).unwrap(), bytes);
+        }
+
+        // The blob the cases above were cut from is decoded.
+        let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+        save.build_component(id)
+            .custom_data(CustomData::Unknown(valid.clone()))
+            .add()
+            .unwrap();
+        let bytes = Writer::new().write(&save).unwrap();
+        assert!(!matches!(
+            parse_save_bytes(&bytes).unwrap().components[0].custom_data,
+            CustomData::Unknown(_)
+        ));
+    }
+}
+
+#[test]
+fn header_only_matches_the_full_parse() {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let paths = std::fs::read_dir(fixtures)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "logicworld"));
+    let mut checked = 0;
+    for path in paths {
+        let bytes = std::fs::read(&path).unwrap();
+        let save = parse_save_bytes(&bytes).unwrap();
+        let header = Parser::from_bytes(&bytes).parse_header_only().unwrap();
+        assert_eq!(
+            header,
+            SaveHeader {
+                game_version: save.game_version.clone(),
+                mod_versions: save.mod_versions.clone(),
+                component_count: save.components.len(),
+                wire_count: save.wires.len(),
+            },
+            "{}",
+            path.display()
+        );
+        checked += 1;
+    }
+    assert!(checked >= 5, "only found {checked} fixtures");
+}
+
+#[test]
+fn parsing_the_same_bytes_twice_gives_equal_saves() {
+    for name in [
+        "wires",
+        "custom_data",
+        "ordering",
+        "subassembly",
+        "non_utf8",
+    ] {
+        let bytes = fixture(name);
+        let first = parse_save_bytes(&bytes).unwrap();
+        let second = parse_save_bytes(&bytes).unwrap();
+        assert_eq!(first, second, "{name}");
+        assert_eq!(first.components, second.components, "{name}");
+        assert_eq!(first.wires, second.wires, "{name}");
+        assert_eq!(first.states, second.states, "{name}");
+    }
+    let bytes = fixture("wires");
+    let mut moved = parse_save_bytes(&bytes).unwrap();
+    moved.components[0].position.x += 1;
+    assert_ne!(moved, parse_save_bytes(&bytes).unwrap());
+}
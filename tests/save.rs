@@ -0,0 +1,199 @@
+//! Editing a save in place.
+
+mod common;
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use common::fixture;
+use logic_world_save::{parse_save_bytes, Address, CompMap, SaveFile, StateId, Version, Writer};
+
+#[test]
+fn cleared_save_has_no_stray_state_bits() {
+    let mut save = parse_save_bytes(&fixture("custom_data")).unwrap();
+    assert!(save.states.iter_on().next().is_some());
+    save.clear_out();
+
+    let off = save
+        .build_component("MHG.Switch")
+        .outputs(1)
+        .switch_on(false)
+        .add()
+        .unwrap();
+    let on = save
+        .build_component("MHG.Switch")
+        .outputs(1)
+        .switch_on(true)
+        .add()
+        .unwrap();
+    save.build_component("MHG.Inverter")
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap();
+    assert!(save.validate().is_empty());
+
+    let bytes = Writer::new().write(&save).unwrap();
+    let parsed = parse_save_bytes(&bytes).unwrap();
+    let on_output = parsed.find_component(on).unwrap().outputs[0];
+    assert_eq!(parsed.states.iter_on().collect::<Vec<_>>(), [on_output]);
+    assert!(!parsed.is_switch_on(off).unwrap());
+    assert_eq!(parsed.highest_state_id, StateId(4));
+}
+
+#[test]
+fn free_state_ids_cross_byte_boundaries() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let ids: Vec<_> = (0..9).map(|_| save.get_free_state_id()).collect();
+    assert_eq!(ids, (1..=9).map(StateId).collect::<Vec<_>>());
+    assert_eq!(save.states.0.len(), 2);
+
+    for id in [7, 8, 9] {
+        save.set_state(StateId(id), true).unwrap();
+    }
+    assert_eq!(save.states.0, [0b1000_0000, 0b0000_0011]);
+    for id in [7, 8, 9] {
+        assert!(save.get_state(StateId(id)).unwrap());
+    }
+    assert!(!save.get_state(StateId(6)).unwrap());
+}
+
+#[test]
+fn free_state_id_grows_a_short_states_buffer() {
+    let mut save = parse_save_bytes(&fixture("wires")).unwrap();
+    // A file whose states buffer stops before its highest id, with a stray bit in it.
+    save.states.0 = vec![0xff];
+    save.highest_state_id = StateId(20);
+
+    let id = save.get_free_state_id();
+    assert_eq!(id, StateId(21));
+    assert_eq!(save.states.0.len(), 3);
+    assert!(!save.get_state(id).unwrap());
+    assert_eq!(save.states.0[0], 0xff);
+    assert!(save.states.0[1..].iter().all(|&byte| byte == 0));
+}
+
+/// A save whose comp map comes from a `HashMap`, which iterates in a different order every time.
+#[cfg(feature = "std")]
+fn save_with_comp_map() -> SaveFile {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let names: HashMap<u16, String> = (1..=50).map(|id| (id, format!("Mod.Part{id}"))).collect();
+    save.comp_map = CompMap::from(names);
+    save
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn comp_map_from_a_hash_map_writes_the_same_bytes() {
+    let bytes = Writer::new().write(&save_with_comp_map()).unwrap();
+    for _ in 0..20 {
+        assert!(Writer::new().write(&save_with_comp_map()).unwrap() == bytes);
+    }
+    let first = bytes
+        .windows(9)
+        .position(|window| window == b"Mod.Part1")
+        .unwrap();
+    assert_eq!(bytes[first - 6..first - 4], 1u16.to_le_bytes());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn building_the_same_save_always_writes_the_same_bytes() {
+    let build = || {
+        let mut save = save_with_comp_map();
+        for i in 0..20 {
+            save.mod_versions
+                .insert(format!("Mod{i}").into(), Version(1, i, 0, 0));
+        }
+        save
+    };
+    let bytes = Writer::new().write(&build()).unwrap();
+    for _ in 0..100 {
+        assert!(Writer::new().write(&build()).unwrap() == bytes);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn failed_atomic_save_leaves_the_old_file_and_backups_alone() {
+    use logic_world_save::BackupPolicy;
+
+    let dir = std::env::temp_dir().join(format!("logic_world_save_atomic_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("world.logicworld");
+
+    let old = parse_save_bytes(&fixture("custom_data")).unwrap();
+    old.save_atomic(&path, BackupPolicy::Keep(2)).unwrap();
+    old.save_atomic(&path, BackupPolicy::Keep(2)).unwrap();
+    let old_bytes = std::fs::read(&path).unwrap();
+    let backup = std::fs::read(dir.join("world.logicworld.bak.1")).unwrap();
+    assert_eq!(backup, old_bytes);
+
+    // A directory where the temporary file goes makes the write fail.
+    std::fs::create_dir(dir.join("world.logicworld.tmp")).unwrap();
+    let mut new = old.clone();
+    new.clear_out();
+    assert!(new.save_atomic(&path, BackupPolicy::Keep(2)).is_err());
+
+    assert_eq!(std::fs::read(&path).unwrap(), old_bytes);
+    assert_eq!(
+        std::fs::read(dir.join("world.logicworld.bak.1")).unwrap(),
+        backup
+    );
+    assert!(!dir.join("world.logicworld.bak.2").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn removed_comp_map_entries_are_gone_by_id_and_by_name() {
+    let mut map = CompMap::with_capacity(0);
+    let inverter = map.ensure("MHG.Inverter");
+    let switch = map.ensure("MHG.Switch");
+    let peg = map.ensure("MHG.Peg");
+
+    assert_eq!(map.remove("MHG.Switch"), Some(switch));
+    assert_eq!(map.remove("MHG.Switch"), None);
+    assert!(!map.contains_name("MHG.Switch"));
+    assert!(!map.contains_id(switch));
+    assert!(map.get_name("MHG.Switch").is_err());
+    assert!(map.get_id(switch).is_err());
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get_name("MHG.Peg").unwrap(), peg);
+    assert_eq!(&**map.get_id(inverter).unwrap(), "MHG.Inverter");
+    assert_eq!(
+        map.iter().collect::<Vec<_>>(),
+        [(inverter, "MHG.Inverter"), (peg, "MHG.Peg")]
+    );
+    // The freed id isn't handed out again while a higher one is in use.
+    assert_eq!(map.ensure("MHG.Switch"), peg + 1);
+}
+
+#[test]
+fn stats_count_everything_in_the_save() {
+    let stats = parse_save_bytes(&fixture("wires")).unwrap().stats();
+    let mut types: Vec<(&str, usize)> = stats
+        .component_types
+        .iter()
+        .map(|(id, &count)| (id.as_str(), count))
+        .collect();
+    types.sort_unstable();
+    assert_eq!(
+        types,
+        [
+            ("MHG.CircuitBoard", 1),
+            ("MHG.Inverter", 3),
+            ("MHG.Switch", 1)
+        ]
+    );
+    assert_eq!(stats.component_count, 5);
+    assert_eq!(stats.wire_count, 3);
+    assert_eq!(stats.state_count, 8);
+    // The switch output and the first inverter's output, `0b1010`.
+    assert_eq!(stats.active_state_count, 2);
+    assert_eq!(stats.mod_count, 1);
+    assert_eq!(stats.game_version, Version(0, 91, 3, 0));
+    assert_eq!(stats.highest_address, Address(6));
+}
@@ -0,0 +1,138 @@
+//! Serde support has to give back exactly what was serialized.
+
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::fixture;
+use logic_world_save::{
+    parse_save_bytes,
+    Address,
+    Color,
+    Component,
+    CustomData,
+    PegAddress,
+    PegType,
+    Quat,
+    SaveFile,
+    StateId,
+    States,
+    Vec3,
+    Version,
+    Wire,
+    Writer,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+fn roundtrip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    let json = serde_json::to_string(value).unwrap();
+    serde_json::from_str(&json).unwrap_or_else(|err| panic!("reading back {json}: {err}"))
+}
+
+#[test]
+fn custom_data_roundtrips() {
+    for data in [
+        CustomData::None,
+        CustomData::Unknown(vec![0, 1, 255]),
+        CustomData::Switch {
+            color: Color::from_rgb(1, 2, 3),
+            on: true,
+        },
+        CustomData::Display { color_mode: 12 },
+        CustomData::Delayer { ticks: 40 },
+        CustomData::Label {
+            text: "hello \"world\"".into(),
+            font_size: 24,
+            color: Color::from_rgb(4, 5, 6),
+        },
+    ] {
+        assert_eq!(roundtrip(&data), data);
+    }
+}
+
+#[test]
+fn component_and_wire_roundtrip() {
+    let component = Component {
+        address: Address(3),
+        parent: Address(1),
+        id: "MHG.AndGate".into(),
+        position: Vec3 {
+            x: -1,
+            y: i32::MAX,
+            z: i32::MIN,
+        },
+        rotation: Quat {
+            x: 0.,
+            y: 0.479_425_55,
+            z: 0.,
+            w: 0.877_582_55,
+        },
+        inputs: vec![StateId(1), StateId(2)],
+        outputs: vec![StateId(-1)],
+        custom_data: CustomData::None,
+    };
+    assert_eq!(roundtrip(&component), component);
+
+    let wire = Wire {
+        start: PegAddress {
+            type_: PegType::Output,
+            component: Address(3),
+            index: 0,
+        },
+        end: PegAddress {
+            type_: PegType::Input,
+            component: Address(4),
+            index: 1,
+        },
+        state_id: StateId(7),
+        rotation: 1.25,
+    };
+    assert_eq!(roundtrip(&wire), wire);
+    assert!(serde_json::to_string(&wire)
+        .unwrap()
+        .contains("\"type\":\"Output\""));
+}
+
+#[test]
+fn states_are_a_hex_string() {
+    let states = States(vec![0x00, 0xab, 0xff]);
+    assert_eq!(serde_json::to_string(&states).unwrap(), "\"00abff\"");
+    assert_eq!(roundtrip(&states), states);
+    assert!(serde_json::from_str::<States>("\"abc\"").is_err());
+    assert!(serde_json::from_str::<States>("\"zz\"").is_err());
+    assert_eq!(roundtrip(&Version(0, 91, 3, 1)), Version(0, 91, 3, 1));
+}
+
+#[test]
+fn json_gives_back_the_same_save_and_bytes() {
+    // The non UTF-8 fixture keeps raw strings, the ordering one an unsorted comp map and mods.
+    for name in [
+        "custom_data",
+        "non_utf8",
+        "ordering",
+        "subassembly",
+        "wires",
+    ] {
+        let bytes = fixture(name);
+        let save = parse_save_bytes(&bytes).unwrap();
+        let json = save.to_json().unwrap();
+        let read = SaveFile::from_json(&json).unwrap();
+        assert_eq!(read, save, "{name}");
+        assert!(Writer::new().write(&read).unwrap() == bytes, "{name}");
+    }
+}
+
+#[test]
+fn json_keeps_the_index_working() {
+    let save = parse_save_bytes(&fixture("wires")).unwrap();
+    let read = SaveFile::from_json(&save.to_json().unwrap()).unwrap();
+    for comp in &save.components {
+        assert_eq!(read.find_component(comp.address), Some(comp));
+        assert_eq!(
+            read.wires_for_component(comp.address),
+            save.wires_for_component(comp.address)
+        );
+    }
+    assert!(SaveFile::from_json("{}").is_err());
+}
@@ -0,0 +1,79 @@
+//! Reading and writing single state bits.
+
+mod common;
+
+use common::fixture;
+use logic_world_save::{parse_save_bytes, Address, SaveFile, StateId, States, Writer};
+
+fn save() -> SaveFile {
+    parse_save_bytes(&fixture("custom_data")).unwrap()
+}
+
+#[test]
+fn set_state_then_get_state_gives_it_back() {
+    let mut save = save();
+    for id in 0..save.state_count() as i32 {
+        for on in [true, false, true] {
+            save.set_state(StateId(id), on).unwrap();
+            assert_eq!(save.get_state(StateId(id)).unwrap(), on, "{id}");
+        }
+    }
+    assert_eq!(save.states.0, [0xFF, 0xFF]);
+}
+
+#[test]
+fn states_outside_the_buffer_are_errors() {
+    let mut save = save();
+    let past_end = StateId(save.state_count() as i32);
+    for id in [past_end, StateId(-1)] {
+        assert!(save.get_state(id).is_err(), "{id}");
+        assert!(save.set_state(id, true).is_err(), "{id}");
+    }
+    assert_eq!(save.state_count(), 16);
+}
+
+#[test]
+fn reset_all_states_clears_every_bit() {
+    let mut save = save();
+    assert!(save.states.iter_on().next().is_some());
+    save.reset_all_states();
+    assert_eq!(save.states.0, [0, 0]);
+    assert_eq!(save.state_count(), 16);
+}
+
+#[test]
+fn lowest_state_id_is_the_least_significant_bit() {
+    // The game saved switch 2 on and switch 3 off, their outputs are state ids 1 and 2.
+    let bytes = fixture("custom_data");
+    let mut save = parse_save_bytes(&bytes).unwrap();
+    assert_eq!(save.states.0[0] & 0b110, 0b010);
+    assert_eq!(save.states.get(StateId(1)), Some(true));
+    assert_eq!(save.states.get(StateId(2)), Some(false));
+
+    save.set_switch_on(Address(3), true).unwrap();
+    let written = Writer::new().write(&save).unwrap();
+    let reparsed = parse_save_bytes(&written).unwrap();
+    assert_eq!(reparsed.states.0[0] & 0b110, 0b110);
+    assert_eq!(reparsed.states.get(StateId(2)), Some(true));
+}
+
+#[test]
+fn states_get_set_and_iter_on() {
+    let mut states = States(vec![0b1000_0001]);
+    assert_eq!(states.get(StateId(0)), Some(true));
+    assert_eq!(states.get(StateId(7)), Some(true));
+    assert_eq!(states.get(StateId(1)), Some(false));
+    assert_eq!(states.get(StateId(8)), None);
+    assert_eq!(states.get(StateId(-1)), None);
+    assert_eq!(states.len_bits(), 8);
+
+    states.set(StateId(17), true);
+    assert_eq!(states.0, [0b1000_0001, 0, 0b10]);
+    assert_eq!(states.len_bits(), 24);
+    states.set(StateId(0), false);
+    states.set(StateId(9), true);
+    assert_eq!(
+        states.iter_on().collect::<Vec<_>>(),
+        [StateId(7), StateId(9), StateId(17)]
+    );
+}
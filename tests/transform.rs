@@ -0,0 +1,312 @@
+//! Moving components around the grid.
+
+mod common;
+
+use common::fixture;
+use logic_world_save::{
+    parse_save_bytes,
+    Address,
+    Axis,
+    BoundingBox,
+    Color,
+    CustomData,
+    Quat,
+    SaveFile,
+    Vec3,
+    Version,
+};
+
+fn save_at(positions: &[Vec3]) -> SaveFile {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    for &position in positions {
+        save.add_component(
+            "MHG.CircuitBoard",
+            Address::ROOT,
+            position,
+            Quat::IDENTITY,
+            0,
+            0,
+            CustomData::None,
+        )
+        .unwrap();
+    }
+    save
+}
+
+fn positions(save: &SaveFile) -> Vec<Vec3> {
+    save.components.iter().map(|comp| comp.position).collect()
+}
+
+#[test]
+fn normalize_positions_moves_the_lowest_corner_to_the_origin() {
+    let mut save = save_at(&[Vec3 { x: -5, y: 10, z: 3 }, Vec3 { x: 7, y: 2, z: 9 }]);
+    let offset = save.normalize_positions().unwrap();
+    assert_eq!(offset, Vec3 { x: 5, y: -2, z: -3 });
+    assert_eq!(
+        positions(&save),
+        [Vec3 { x: 0, y: 8, z: 0 }, Vec3 { x: 12, y: 0, z: 6 }]
+    );
+}
+
+#[test]
+fn normalize_positions_fails_without_moving_on_overflow() {
+    let original = [
+        Vec3 {
+            x: i32::MIN,
+            y: 0,
+            z: 0,
+        },
+        Vec3 {
+            x: i32::MAX,
+            y: 0,
+            z: 0,
+        },
+    ];
+    let mut save = save_at(&original);
+    assert!(save.normalize_positions().is_err());
+    assert_eq!(positions(&save), original);
+
+    let original = [
+        Vec3 {
+            x: i32::MIN + 1,
+            y: 0,
+            z: 0,
+        },
+        Vec3 {
+            x: i32::MAX,
+            y: 0,
+            z: 0,
+        },
+    ];
+    let mut save = save_at(&original);
+    assert!(save.normalize_positions().is_err());
+    assert_eq!(positions(&save), original);
+}
+
+#[test]
+fn duplicate_component_gets_its_own_address_and_state_ids() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let board = save.build_component("MHG.CircuitBoard").add().unwrap();
+    let switch = save
+        .build_component("MHG.Switch")
+        .parent(board)
+        .at(1, 2, 3)
+        .outputs(1)
+        .custom_data(CustomData::Switch {
+            color: Color::from_rgb(4, 5, 6),
+            on: true,
+        })
+        .add()
+        .unwrap();
+    let original = save.find_component(switch).unwrap().clone();
+
+    let copy = save
+        .duplicate_component(switch, Vec3 { x: 10, y: 0, z: 0 })
+        .unwrap();
+
+    assert_ne!(copy, switch);
+    assert_eq!(save.find_component(switch).unwrap(), &original);
+    let duplicate = save.find_component(copy).unwrap();
+    assert_eq!(duplicate.position, Vec3 { x: 11, y: 2, z: 3 });
+    assert_eq!(duplicate.parent, board);
+    assert_eq!(duplicate.custom_data, original.custom_data);
+    assert_ne!(duplicate.outputs, original.outputs);
+    assert!(save.is_switch_on(copy).unwrap());
+    assert!(save.get_state(duplicate.outputs[0]).unwrap());
+    assert!(save.validate().is_empty());
+}
+
+#[test]
+fn duplicate_component_starts_its_inputs_off() {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let gate = save
+        .build_component("MHG.Inverter")
+        .inputs(1)
+        .outputs(1)
+        .add()
+        .unwrap();
+    let (input, output) = {
+        let comp = save.find_component(gate).unwrap();
+        (comp.inputs[0], comp.outputs[0])
+    };
+    save.set_state(input, true).unwrap();
+
+    let copy = save.duplicate_component(gate, Vec3::zero()).unwrap();
+
+    let duplicate = save.find_component(copy).unwrap().clone();
+    let mut ids = vec![input, output, duplicate.inputs[0], duplicate.outputs[0]];
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 4);
+    assert!(!save.get_state(duplicate.inputs[0]).unwrap());
+    assert!(save.get_state(input).unwrap());
+}
+
+#[test]
+fn flipping_twice_restores_the_save() {
+    let original = parse_save_bytes(&fixture("wires")).unwrap();
+    for axis in [Axis::X, Axis::Y, Axis::Z] {
+        let mut save = original.clone();
+        save.flip(axis).unwrap();
+        assert_ne!(save, original);
+        save.flip(axis).unwrap();
+        assert_eq!(save, original);
+    }
+}
+
+#[test]
+fn flipping_mirrors_the_bounding_box() {
+    let mut save = save_at(&[Vec3 { x: -5, y: 10, z: 3 }, Vec3 { x: 7, y: 2, z: 9 }]);
+    let before = save.bounding_box().unwrap();
+    save.flip(Axis::X).unwrap();
+    let after = save.bounding_box().unwrap();
+    assert_eq!(after.size(), before.size());
+    assert_eq!(
+        after,
+        BoundingBox {
+            min: Vec3 { x: -7, y: 2, z: 3 },
+            max: Vec3 { x: 5, y: 10, z: 9 },
+        }
+    );
+}
+
+#[test]
+fn flipping_the_lowest_coordinate_fails_without_changes() {
+    let mut save = save_at(&[
+        Vec3 { x: 1, y: 2, z: 3 },
+        Vec3 {
+            x: 0,
+            y: i32::MIN,
+            z: 0,
+        },
+    ]);
+    let original = save.clone();
+    assert!(save.flip(Axis::Y).is_err());
+    assert_eq!(save, original);
+    save.flip(Axis::X).unwrap();
+}
+
+#[test]
+fn bounding_box_of_an_empty_save_is_none() {
+    assert_eq!(save_at(&[]).bounding_box(), None);
+}
+
+#[test]
+fn bounding_box_of_one_component_is_a_point() {
+    let position = Vec3 { x: 4, y: -9, z: 2 };
+    let bounds = save_at(&[position]).bounding_box().unwrap();
+    assert_eq!(
+        bounds,
+        BoundingBox {
+            min: position,
+            max: position,
+        }
+    );
+    assert_eq!(bounds.size(), Vec3::zero());
+    assert!(bounds.contains(&position));
+    assert!(bounds.intersects(&bounds));
+    assert_eq!(bounds.center_f32(), (4., -9., 2.));
+}
+
+#[test]
+fn bounding_box_size_and_expand_saturate() {
+    let bounds = BoundingBox {
+        min: Vec3 {
+            x: i32::MIN,
+            y: -10,
+            z: 0,
+        },
+        max: Vec3 {
+            x: i32::MAX,
+            y: 10,
+            z: i32::MAX - 1,
+        },
+    };
+    assert_eq!(
+        bounds.size(),
+        Vec3 {
+            x: i32::MAX,
+            y: 20,
+            z: i32::MAX - 1,
+        }
+    );
+    assert_eq!(
+        bounds.expand(5),
+        BoundingBox {
+            min: Vec3 {
+                x: i32::MIN,
+                y: -15,
+                z: -5,
+            },
+            max: Vec3 {
+                x: i32::MAX,
+                y: 15,
+                z: i32::MAX,
+            },
+        }
+    );
+}
+
+#[test]
+fn translate_components_moves_nothing_if_one_overflows() {
+    let original = [
+        Vec3 { x: 0, y: 0, z: 0 },
+        Vec3 {
+            x: 0,
+            y: i32::MAX - 5,
+            z: 0,
+        },
+        Vec3 { x: 1, y: 1, z: 1 },
+    ];
+    let mut save = save_at(&original);
+    let addresses: Vec<Address> = save.components.iter().map(|comp| comp.address).collect();
+    let up = Vec3 { x: 0, y: 10, z: 0 };
+
+    assert!(save.translate_components(up, None).is_err());
+    assert_eq!(positions(&save), original);
+    assert!(save
+        .translate_components(up, Some(&addresses[..2]))
+        .is_err());
+    assert_eq!(positions(&save), original);
+
+    // Leaving the component at the edge out lets the others move.
+    save.translate_components(up, Some(&[addresses[0], addresses[2]]))
+        .unwrap();
+    assert_eq!(
+        positions(&save),
+        [
+            Vec3 { x: 0, y: 10, z: 0 },
+            original[1],
+            Vec3 { x: 1, y: 11, z: 1 },
+        ]
+    );
+}
+
+#[test]
+fn replace_component_type_renames_every_match() {
+    let mut save = parse_save_bytes(&fixture("wires")).unwrap();
+    let inverter_id = save.comp_map.get_name("MHG.Inverter").unwrap();
+
+    assert_eq!(save.replace_component_type("MHG.Inverter", "MHG.Buffer"), 3);
+    assert_eq!(save.comp_map.get_name("MHG.Inverter").unwrap(), inverter_id);
+    assert!(save.comp_map.contains_name("MHG.Buffer"));
+
+    let bytes = save.to_bytes().unwrap();
+    let reparsed = parse_save_bytes(&bytes).unwrap();
+    let ids: Vec<&str> = reparsed.components.iter().map(|comp| &*comp.id).collect();
+    assert_eq!(
+        ids,
+        [
+            "MHG.CircuitBoard",
+            "MHG.Switch",
+            "MHG.Buffer",
+            "MHG.Buffer",
+            "MHG.Buffer",
+        ]
+    );
+
+    let entries = save.comp_map.len();
+    assert_eq!(save.replace_component_type("Mod.Missing", "Mod.Other"), 0);
+    assert!(!save.comp_map.contains_name("Mod.Other"));
+    assert_eq!(save.comp_map.len(), entries);
+}
@@ -0,0 +1,274 @@
+//! The small value types a save is made of.
+
+mod common;
+
+use common::fixture;
+#[cfg(feature = "std")]
+use logic_world_save::Vec3f;
+use logic_world_save::{
+    parse_save_bytes,
+    Address,
+    Color,
+    PegAddress,
+    PegType,
+    Quat,
+    StateId,
+    Vec3,
+    Version,
+    Wire,
+};
+
+#[test]
+fn versions_order_field_by_field() {
+    let mut versions = [
+        Version(1, 0, 0, 0),
+        Version(0, 91, 3, 0),
+        Version(0, 10, 0, 0),
+        Version(0, 91, 2, 99),
+        Version(0, 91, 3, 1),
+    ];
+    versions.sort();
+    assert_eq!(
+        versions,
+        [
+            Version(0, 10, 0, 0),
+            Version(0, 91, 2, 99),
+            Version(0, 91, 3, 0),
+            Version(0, 91, 3, 1),
+            Version(1, 0, 0, 0),
+        ]
+    );
+    assert!(Version(0, 91, 3, 0).is_compatible_with(&Version(0, 91, 3, 5)));
+    assert!(!Version(0, 91, 2, 9).is_compatible_with(&Version(0, 91, 3, 0)));
+}
+
+#[test]
+fn versions_parse_back_from_their_display() {
+    for version in [Version(0, 91, 3, 0), Version(-1, 0, 2_000_000, 7)] {
+        assert_eq!(version.to_string().parse::<Version>().unwrap(), version);
+    }
+    assert_eq!("0.91.3.0".parse::<Version>().unwrap(), Version(0, 91, 3, 0));
+    for invalid in ["", "0.91.3", "0.91.3.0.1", "0.91.x.0", "0..3.0"] {
+        assert!(invalid.parse::<Version>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn wire_display() {
+    let wire = Wire {
+        start: PegAddress {
+            type_: PegType::Input,
+            component: Address(7),
+            index: 2,
+        },
+        end: PegAddress {
+            type_: PegType::Input,
+            component: Address(9),
+            index: 0,
+        },
+        state_id: StateId(4),
+        rotation: 0.,
+    };
+    assert_eq!(wire.to_string(), "7:2(input) → 9:0");
+
+    let save = parse_save_bytes(&fixture("wires")).unwrap();
+    assert_eq!(save.wires[0].to_string(), "3:0(output) → 4:0");
+}
+
+#[test]
+fn floats_compare_equal_only_closer_than_epsilon() {
+    let wire = Wire {
+        start: PegAddress {
+            type_: PegType::Output,
+            component: Address(1),
+            index: 0,
+        },
+        end: PegAddress {
+            type_: PegType::Input,
+            component: Address(2),
+            index: 0,
+        },
+        state_id: StateId(1),
+        rotation: 1.,
+    };
+    let nudged = |rotation| Wire {
+        rotation,
+        ..wire.clone()
+    };
+    assert_eq!(wire, nudged(1. + f32::EPSILON / 2.));
+    assert_ne!(wire, nudged(1. + f32::EPSILON));
+
+    let quat = |w| Quat {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+        w,
+    };
+    assert_eq!(quat(1.), Quat::IDENTITY);
+    assert_ne!(quat(1. + f32::EPSILON), Quat::IDENTITY);
+}
+
+#[test]
+fn save_display_summarizes_the_save() {
+    let save = parse_save_bytes(&fixture("wires")).unwrap();
+    assert_eq!(
+        save.to_string(),
+        [
+            "Game version 0.91.3.0",
+            "1 mods",
+            "5 components",
+            "  1 MHG.CircuitBoard",
+            "  3 MHG.Inverter",
+            "  1 MHG.Switch",
+            "3 wires",
+            "8 states",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn colors_parse_from_hex() {
+    let color = Color::from_hex("#1A2b3C").unwrap();
+    assert_eq!(color, Color::from_rgb(0x1A, 0x2B, 0x3C));
+    assert_eq!(
+        Color::from_hex("ff0080").unwrap(),
+        Color::from_rgb(255, 0, 128)
+    );
+    assert_eq!(Color::from_hex(&color.to_string()).unwrap(), color);
+    for invalid in [
+        "", "#", "#12345", "#1234567", "#12345G", "##123456", "#12é45",
+    ] {
+        assert!(Color::from_hex(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn colors_lerp_between_the_ends() {
+    let black = Color::from_rgb(0, 0, 0);
+    let white = Color::from_rgb(255, 255, 255);
+    assert_eq!(black.lerp(white, 0.), black);
+    assert_eq!(black.lerp(white, 1.), white);
+    assert_eq!(black.lerp(white, 0.5), Color::from_rgb(128, 128, 128));
+    assert_eq!(
+        Color::from_rgb(10, 200, 0).lerp(Color::from_rgb(20, 100, 255), 0.25),
+        Color::from_rgb(13, 175, 64)
+    );
+    // `t` is clamped.
+    assert_eq!(black.lerp(white, -3.), black);
+    assert_eq!(black.lerp(white, 7.), white);
+}
+
+#[test]
+fn vec3_arithmetic() {
+    let a = Vec3 { x: 1, y: -2, z: 3 };
+    let b = Vec3 {
+        x: 10,
+        y: 20,
+        z: -30,
+    };
+    assert_eq!(
+        a + b,
+        Vec3 {
+            x: 11,
+            y: 18,
+            z: -27
+        }
+    );
+    assert_eq!(
+        a - b,
+        Vec3 {
+            x: -9,
+            y: -22,
+            z: 33
+        }
+    );
+    assert_eq!(a * 4, Vec3 { x: 4, y: -8, z: 12 });
+    assert_eq!(-a, Vec3 { x: -1, y: 2, z: -3 });
+    assert_eq!(a + Vec3::zero(), a);
+    assert_eq!(a - a, Vec3::zero());
+    assert_eq!(a.distance_squared(&b), 81 + 484 + 1089);
+    assert_eq!(b.distance_squared(&a), a.distance_squared(&b));
+}
+
+#[test]
+fn vec3_checked_arithmetic_catches_overflow() {
+    let max = Vec3 {
+        x: i32::MAX,
+        y: 0,
+        z: 0,
+    };
+    let one = Vec3 { x: 1, y: 1, z: 1 };
+    assert_eq!(max.checked_add(&one), None);
+    assert_eq!(
+        max.checked_sub(&one),
+        Some(Vec3 {
+            x: i32::MAX - 1,
+            y: -1,
+            z: -1,
+        })
+    );
+    assert_eq!((-max).checked_sub(&Vec3 { x: 2, y: 0, z: 0 }), None);
+    // The squared distance between far corners doesn't fit, it saturates instead.
+    let min = Vec3 {
+        x: i32::MIN,
+        y: i32::MIN,
+        z: i32::MIN,
+    };
+    let far = Vec3 {
+        x: i32::MAX,
+        y: i32::MAX,
+        z: i32::MAX,
+    };
+    assert_eq!(min.distance_squared(&far), i64::MAX);
+    assert_eq!(
+        Vec3::zero().distance_squared(&max),
+        (i32::MAX as i64).pow(2)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn quats_normalize_to_unit_length() {
+    let length = |q: Quat| (q.x * q.x + q.y * q.y + q.z * q.z + q.w * q.w).sqrt();
+    let q = Quat {
+        x: 1.,
+        y: 2.,
+        z: 2.,
+        w: 4.,
+    };
+    let unit = q.normalize();
+    assert!((length(unit) - 1.).abs() <= f32::EPSILON);
+    assert_eq!(
+        unit,
+        Quat {
+            x: 0.2,
+            y: 0.4,
+            z: 0.4,
+            w: 0.8,
+        }
+    );
+    assert_eq!(unit.normalize(), unit);
+    assert_eq!(Quat::IDENTITY.normalize(), Quat::IDENTITY);
+    let zero = Quat {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+        w: 0.,
+    };
+    assert_eq!(zero.normalize(), Quat::IDENTITY);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn quats_rotate_vectors() {
+    let quarter = Quat::from_axis_angle(Vec3f(0., 5., 0.), std::f32::consts::FRAC_PI_2);
+    assert!((quarter.y - std::f32::consts::FRAC_1_SQRT_2).abs() <= f32::EPSILON);
+    let Vec3f(x, y, z) = quarter.rotate_vec3(Vec3f(1., 0., 0.));
+    assert!(x.abs() < 1e-6 && y.abs() < 1e-6 && (z + 1.).abs() < 1e-6);
+
+    let half = quarter.multiply(&quarter);
+    let Vec3f(x, _, z) = half.rotate_vec3(Vec3f(1., 0., 0.));
+    assert!((x + 1.).abs() < 1e-6 && z.abs() < 1e-6);
+    assert_eq!(quarter.multiply(&quarter.conjugate()), Quat::IDENTITY);
+}
@@ -0,0 +1,182 @@
+//! Each kind of inconsistency `validate` looks for, on an otherwise valid save.
+
+use logic_world_save::{
+    Address,
+    PegAddress,
+    PegType,
+    SaveFile,
+    Severity,
+    StateId,
+    ValidationError,
+    Version,
+    Wire,
+    WriteIssue,
+    Writer,
+};
+
+/// An inverter wired into a buffer, which is valid as it is.
+fn valid() -> (SaveFile, Address, Address) {
+    let mut save = SaveFile::empty(Version(0, 0, 0, 0));
+    let gate =
+        |save: &mut SaveFile, id| save.build_component(id).inputs(1).outputs(1).add().unwrap();
+    let inverter = gate(&mut save, "MHG.Inverter");
+    let buffer = gate(&mut save, "MHG.Buffer");
+    save.add_wire(
+        (inverter.0, PegType::Output, 0),
+        (buffer.0, PegType::Input, 0),
+    )
+    .unwrap();
+    assert_eq!(save.validate(), []);
+    (save, inverter, buffer)
+}
+
+#[test]
+fn duplicate_address() {
+    let (mut save, inverter, _) = valid();
+    let copy = save.find_component(inverter).unwrap().clone();
+    save.components.push(copy.clone());
+    save.components.push(copy);
+    assert_eq!(
+        save.validate(),
+        [ValidationError::DuplicateAddress { address: inverter }]
+    );
+    assert_eq!(
+        Writer::validate(&save),
+        Err(vec![WriteIssue::DuplicateAddress { address: inverter }])
+    );
+    assert!(Writer::new().write(&save).is_err());
+}
+
+#[test]
+fn dangling_wire() {
+    let (mut save, ..) = valid();
+    save.wires[0].start.component = Address(99);
+    let errors = save.validate();
+    assert_eq!(
+        errors,
+        [ValidationError::DanglingWire {
+            wire: 0,
+            address: Address(99),
+        }]
+    );
+    assert_eq!(errors[0].severity(), Severity::Error);
+    assert_eq!(save.validate_wires(), errors);
+}
+
+#[test]
+fn peg_index_out_of_range() {
+    let (mut save, _, buffer) = valid();
+    save.wires[0].end.index = 1;
+    assert_eq!(
+        save.validate(),
+        [ValidationError::PegIndexOutOfRange {
+            wire: 0,
+            address: buffer,
+            index: 1,
+        }]
+    );
+
+    save.wires[0].end.index = -1;
+    assert_eq!(
+        save.validate(),
+        [ValidationError::PegIndexOutOfRange {
+            wire: 0,
+            address: buffer,
+            index: -1,
+        }]
+    );
+}
+
+#[test]
+fn component_state_out_of_range() {
+    let (mut save, inverter, _) = valid();
+    let state_id = StateId(save.states.len_bits() as i32);
+    save.find_component_mut(inverter).unwrap().inputs[0] = state_id;
+    let errors = save.validate();
+    assert_eq!(
+        errors,
+        [ValidationError::ComponentStateOutOfRange {
+            address: inverter,
+            state_id,
+        }]
+    );
+    // The writer pads the states buffer, so this one is only a warning.
+    assert_eq!(errors[0].severity(), Severity::Warning);
+}
+
+#[test]
+fn wire_state_out_of_range() {
+    let (mut save, inverter, buffer) = valid();
+    save.wires.push(Wire {
+        start: PegAddress {
+            type_: PegType::Output,
+            component: inverter,
+            index: 0,
+        },
+        end: PegAddress {
+            type_: PegType::Output,
+            component: buffer,
+            index: 0,
+        },
+        state_id: StateId(-4),
+        rotation: 0.,
+    });
+    let errors = save.validate();
+    assert_eq!(
+        errors,
+        [ValidationError::WireStateOutOfRange {
+            wire: 1,
+            state_id: StateId(-4),
+        }]
+    );
+    assert_eq!(errors[0].severity(), Severity::Warning);
+}
+
+#[test]
+fn missing_parent() {
+    let (mut save, inverter, _) = valid();
+    save.find_component_mut(inverter).unwrap().parent = Address(42);
+    assert_eq!(
+        save.validate(),
+        [ValidationError::MissingParent {
+            address: inverter,
+            parent: Address(42),
+        }]
+    );
+}
+
+#[test]
+fn self_parent() {
+    let (mut save, inverter, _) = valid();
+    save.find_component_mut(inverter).unwrap().parent = inverter;
+    assert_eq!(
+        save.validate(),
+        [ValidationError::SelfParent { address: inverter }]
+    );
+}
+
+#[test]
+fn parent_cycle_is_reported_once() {
+    let (mut save, inverter, buffer) = valid();
+    let board = save.build_component("MHG.CircuitBoard").add().unwrap();
+    save.build_component("MHG.CircuitBoard")
+        .parent(board)
+        .add()
+        .unwrap();
+    // board -> inverter -> buffer -> board, with another board placed on the cycle.
+    for (address, parent) in [(board, inverter), (inverter, buffer), (buffer, board)] {
+        save.find_component_mut(address).unwrap().parent = parent;
+    }
+
+    let errors = save.validate();
+    assert_eq!(
+        errors,
+        [ValidationError::ParentCycle {
+            addresses: vec![inverter, buffer, board],
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        format!("Components form a parent cycle: {inverter} -> {buffer} -> {board} -> {inverter}")
+    );
+}